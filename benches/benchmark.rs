@@ -1,14 +1,15 @@
 extern crate criterion;
-extern crate jar;
+extern crate jed;
 use criterion::{criterion_group, criterion_main, Criterion};
-use jar::vm::{self, VM};
+use jed::VM;
 use std::hint::black_box;
 
 fn criterion_benchmark(c: &mut Criterion) {
     c.bench_function("vm - mystack", |b| {
         b.iter(|| {
-            black_box(VM::from_string(
-                "func fib 1\n\t\
+            let _ = black_box(
+                VM::from_string(
+                    "func fib 1\n\t\
              store_name n\n\t\
 
              push_name n\n\t\
@@ -32,10 +33,12 @@ fn criterion_benchmark(c: &mut Criterion) {
           func main 0\n\t\
               push_lit 35\n\t\
               call fib\n\t\
-          done"
-                    .to_owned(),
-            ))
-            .run();
+          done",
+                    false,
+                )
+                .expect("parses")
+                .run(),
+            );
         })
     });
 }
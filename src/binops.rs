@@ -1,8 +1,11 @@
-use std::{convert::TryInto, fmt::Display};
+use std::{
+    convert::{TryFrom, TryInto},
+    fmt::Display,
+};
 
 use crate::{
     error::ProgramErrorKind,
-    object::{Object, ObjectData},
+    object::{Object, ObjectData, ObjectKind},
 };
 
 #[repr(u8)]
@@ -24,6 +27,10 @@ pub enum BinOpKind {
 
     Power,
     Root,
+
+    // after the rest so older Bytecode keeps its codes
+    NotEq,
+    FloorDiv,
 }
 
 impl Display for BinOpKind {
@@ -43,13 +50,15 @@ impl Display for BinOpKind {
             BinOpKind::Or => write!(f, "||"),
             BinOpKind::Power => write!(f, "pow"),
             BinOpKind::Root => write!(f, "root"),
+            BinOpKind::NotEq => write!(f, "!="),
+            BinOpKind::FloorDiv => write!(f, "//"),
         }
     }
 }
 
-impl From<&str> for BinOpKind {
-    fn from(value: &str) -> Self {
-        match value {
+impl BinOpKind {
+    pub fn parse(value: &str) -> Option<Self> {
+        let kind = match value {
             "+" => BinOpKind::Add,
             "-" => BinOpKind::Sub,
             "*" => BinOpKind::Mul,
@@ -64,15 +73,51 @@ impl From<&str> for BinOpKind {
             "||" => BinOpKind::Or,
             "pow" => BinOpKind::Power,
             "root" => BinOpKind::Root,
-            _ => panic!("Binary operator not implemented: '{}'", value),
-        }
+            "!=" => BinOpKind::NotEq,
+            "//" => BinOpKind::FloorDiv,
+            _ => return None,
+        };
+        Some(kind)
+    }
+}
+
+impl From<&str> for BinOpKind {
+    fn from(value: &str) -> Self {
+        BinOpKind::parse(value)
+            .unwrap_or_else(|| panic!("Binary operator not implemented: '{}'", value))
+    }
+}
+
+impl BinOpKind {
+    /// Every kind, in the order of their codes.
+    pub const ALL: [BinOpKind; 16] = [
+        BinOpKind::Add,
+        BinOpKind::Sub,
+        BinOpKind::Mul,
+        BinOpKind::Div,
+        BinOpKind::Mod,
+        BinOpKind::Eq,
+        BinOpKind::LessEq,
+        BinOpKind::GreatEq,
+        BinOpKind::Lesser,
+        BinOpKind::Greater,
+        BinOpKind::And,
+        BinOpKind::Or,
+        BinOpKind::Power,
+        BinOpKind::Root,
+        BinOpKind::NotEq,
+        BinOpKind::FloorDiv,
+    ];
+
+    /// The kind stored as `code` in Bytecode.
+    pub fn from_code(code: u8) -> Option<Self> {
+        BinOpKind::ALL.get(usize::from(code)).copied()
     }
 }
 
 impl From<u8> for BinOpKind {
     fn from(value: u8) -> Self {
-        assert!(value <= 12);
-        unsafe { std::mem::transmute(value) }
+        BinOpKind::from_code(value).unwrap_or_else(|| panic!("no binary operator {}", value))
     }
 }
 
@@ -118,16 +163,38 @@ pub fn mul(lhs: ObjectData, rhs: ObjectData) -> Result<Object, ProgramErrorKind>
         _ => Err(ProgramErrorKind::BinopError(BinOpKind::Mul, lhs, rhs)),
     }
 }
+/// Integer division rounds toward zero, `//` is the one that rounds down.
 pub fn div(lhs: ObjectData, rhs: ObjectData) -> Result<Object, ProgramErrorKind> {
     match (lhs, rhs) {
-        (ObjectData::Integer(_), ObjectData::Integer(_)) => todo!(),
+        (ObjectData::Integer(_), ObjectData::Integer(0)) => Err(ProgramErrorKind::DivisionByZero),
+        (ObjectData::Integer(left), ObjectData::Integer(right)) => match left.checked_div(right) {
+            Some(v) => Ok(v.into()),
+            None => Err(ProgramErrorKind::Overflow(BinOpKind::Div, left, right)),
+        },
         (ObjectData::Float(_, _), ObjectData::Float(_, _)) => todo!(),
         _ => Err(ProgramErrorKind::BinopError(BinOpKind::Div, lhs, rhs)),
     }
 }
+pub fn floor_div(lhs: ObjectData, rhs: ObjectData) -> Result<Object, ProgramErrorKind> {
+    match (lhs, rhs) {
+        (ObjectData::Integer(_), ObjectData::Integer(0)) => Err(ProgramErrorKind::DivisionByZero),
+        (ObjectData::Integer(left), ObjectData::Integer(right)) => match left.checked_div(right) {
+            // truncated toward zero, so one too high when the signs differ and it isn't exact
+            Some(v) if left % right != 0 && (left < 0) != (right < 0) => Ok((v - 1).into()),
+            Some(v) => Ok(v.into()),
+            None => Err(ProgramErrorKind::Overflow(BinOpKind::FloorDiv, left, right)),
+        },
+        _ => Err(ProgramErrorKind::BinopError(BinOpKind::FloorDiv, lhs, rhs)),
+    }
+}
+/// The remainder takes the sign of `lhs`, like Rust's `%`.
 pub fn modulus(lhs: ObjectData, rhs: ObjectData) -> Result<Object, ProgramErrorKind> {
     match (lhs, rhs) {
-        (ObjectData::Integer(left), ObjectData::Integer(right)) => Ok((left % right).into()),
+        (ObjectData::Integer(_), ObjectData::Integer(0)) => Err(ProgramErrorKind::DivisionByZero),
+        (ObjectData::Integer(left), ObjectData::Integer(right)) => match left.checked_rem(right) {
+            Some(v) => Ok(v.into()),
+            None => Err(ProgramErrorKind::Overflow(BinOpKind::Mod, left, right)),
+        },
         (ObjectData::Float(_, _), ObjectData::Float(_, _)) => todo!(),
         _ => Err(ProgramErrorKind::BinopError(BinOpKind::Mod, lhs, rhs)),
     }
@@ -135,20 +202,28 @@ pub fn modulus(lhs: ObjectData, rhs: ObjectData) -> Result<Object, ProgramErrorK
 pub fn eq(lhs: ObjectData, rhs: ObjectData) -> Result<Object, ProgramErrorKind> {
     match (lhs, rhs) {
         (ObjectData::Integer(left), ObjectData::Integer(right)) => Ok((left == right).into()),
+        (ObjectData::Bool(left), ObjectData::Bool(right)) => Ok((left == right).into()),
         (ObjectData::Float(_, _), ObjectData::Float(_, _)) => todo!(),
         _ => Err(ProgramErrorKind::BinopError(BinOpKind::Eq, lhs, rhs)),
     }
 }
+pub fn not_eq(lhs: ObjectData, rhs: ObjectData) -> Result<Object, ProgramErrorKind> {
+    match (lhs, rhs) {
+        (ObjectData::Integer(left), ObjectData::Integer(right)) => Ok((left != right).into()),
+        (ObjectData::Bool(left), ObjectData::Bool(right)) => Ok((left != right).into()),
+        _ => Err(ProgramErrorKind::BinopError(BinOpKind::NotEq, lhs, rhs)),
+    }
+}
 pub fn lesser(lhs: ObjectData, rhs: ObjectData) -> Result<Object, ProgramErrorKind> {
     match (lhs, rhs) {
-        (ObjectData::Integer(_), ObjectData::Integer(_)) => todo!(),
+        (ObjectData::Integer(left), ObjectData::Integer(right)) => Ok((left < right).into()),
         (ObjectData::Float(_, _), ObjectData::Float(_, _)) => todo!(),
         _ => Err(ProgramErrorKind::BinopError(BinOpKind::Lesser, lhs, rhs)),
     }
 }
 pub fn greater(lhs: ObjectData, rhs: ObjectData) -> Result<Object, ProgramErrorKind> {
     match (lhs, rhs) {
-        (ObjectData::Integer(_), ObjectData::Integer(_)) => todo!(),
+        (ObjectData::Integer(left), ObjectData::Integer(right)) => Ok((left > right).into()),
         (ObjectData::Float(_, _), ObjectData::Float(_, _)) => todo!(),
         _ => Err(ProgramErrorKind::BinopError(BinOpKind::Greater, lhs, rhs)),
     }
@@ -162,7 +237,7 @@ pub fn lesseq(lhs: ObjectData, rhs: ObjectData) -> Result<Object, ProgramErrorKi
 }
 pub fn greateq(lhs: ObjectData, rhs: ObjectData) -> Result<Object, ProgramErrorKind> {
     match (lhs, rhs) {
-        (ObjectData::Integer(_), ObjectData::Integer(_)) => todo!(),
+        (ObjectData::Integer(left), ObjectData::Integer(right)) => Ok((left >= right).into()),
         (ObjectData::Float(_, _), ObjectData::Float(_, _)) => todo!(),
         _ => Err(ProgramErrorKind::BinopError(BinOpKind::GreatEq, lhs, rhs)),
     }
@@ -182,21 +257,134 @@ pub fn or(lhs: ObjectData, rhs: ObjectData) -> Result<Object, ProgramErrorKind>
 
 pub fn pow(lhs: ObjectData, rhs: ObjectData) -> Result<Object, ProgramErrorKind> {
     match (lhs, rhs) {
+        (ObjectData::Integer(_), ObjectData::Integer(right)) if right < 0 => {
+            Err(ProgramErrorKind::IntegerToUnsigned)
+        }
         (ObjectData::Integer(left), ObjectData::Integer(right)) => {
-            Ok(left.pow(right.try_into().expect("no")).into())
+            match u32::try_from(right).ok().and_then(|exp| left.checked_pow(exp)) {
+                Some(v) => Ok(v.into()),
+                None => Err(ProgramErrorKind::Overflow(BinOpKind::Power, left, right)),
+            }
         }
         (ObjectData::Float(_, _), ObjectData::Float(_, _)) => todo!(),
-        _ => Err(ProgramErrorKind::BinopError(BinOpKind::Or, lhs, rhs)),
+        _ => Err(ProgramErrorKind::BinopError(BinOpKind::Power, lhs, rhs)),
     }
 }
 
+/// The `rhs`th root of `lhs`, on integers it's rounded toward zero.
 pub fn root(lhs: ObjectData, rhs: ObjectData) -> Result<Object, ProgramErrorKind> {
     match (lhs, rhs) {
+        (ObjectData::Integer(_), ObjectData::Integer(0)) => Err(ProgramErrorKind::DivisionByZero),
+        (ObjectData::Integer(_), ObjectData::Integer(right)) if right < 0 => {
+            Err(ProgramErrorKind::IntegerToUnsigned)
+        }
+        // even roots of negative numbers aren't integers
+        (ObjectData::Integer(left), ObjectData::Integer(right)) if left < 0 && right % 2 == 0 => {
+            Err(ProgramErrorKind::IntegerToUnsigned)
+        }
         (ObjectData::Integer(left), ObjectData::Integer(right)) => {
-            println!("root(int, int): do not use");
-            Ok(left.pow((1 / right).try_into().expect("no")).into())
+            // only the first root of isize::MIN doesn't fit back into an isize
+            let root = integer_root(left.unsigned_abs(), right.unsigned_abs());
+            let Ok(root) = isize::try_from(root) else {
+                return Ok(left.into());
+            };
+            Ok((if left < 0 { -root } else { root }).into())
         }
         (ObjectData::Float(_, _), ObjectData::Float(_, _)) => todo!(),
-        _ => Err(ProgramErrorKind::BinopError(BinOpKind::Or, lhs, rhs)),
+        _ => Err(ProgramErrorKind::BinopError(BinOpKind::Root, lhs, rhs)),
+    }
+}
+
+/// The largest `r` with `r.pow(n) <= value`, by bisection.
+fn integer_root(value: usize, n: usize) -> usize {
+    let fits = |r: usize| {
+        u32::try_from(n)
+            .ok()
+            .and_then(|n| r.checked_pow(n))
+            .is_some_and(|p| p <= value)
+    };
+    let (mut low, mut high) = (0, value);
+    while low < high {
+        let mid = low + (high - low).div_ceil(2);
+        if fits(mid) {
+            low = mid;
+        } else {
+            high = mid - 1;
+        }
+    }
+    low
+}
+
+/// `neg`, `0 - obj` for an `Integer`.
+pub fn neg(obj: Object) -> Result<Object, ProgramErrorKind> {
+    match obj.data {
+        ObjectData::Integer(n) => match n.checked_neg() {
+            Some(v) => Ok(v.into()),
+            None => Err(ProgramErrorKind::Overflow(BinOpKind::Sub, 0, n)),
+        },
+        _ => Err(ProgramErrorKind::TypeError(ObjectKind::Integer, obj.kind)),
+    }
+}
+
+/// `not`, flips a `Bool`.
+pub fn not(obj: Object) -> Result<Object, ProgramErrorKind> {
+    match obj.data {
+        ObjectData::Bool(b) => Ok((!b).into()),
+        _ => Err(ProgramErrorKind::TypeError(ObjectKind::Bool, obj.kind)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{div, eq, floor_div, modulus, not_eq, pow, root, BinOpKind};
+    use crate::{error::ProgramErrorKind, object::ObjectData};
+
+    #[test]
+    fn codes_round_trip() {
+        for kind in BinOpKind::ALL {
+            let code = kind as u8;
+            assert_eq!(BinOpKind::from_code(code).map(|k| k as u8), Some(code));
+            assert_eq!(BinOpKind::parse(&kind.to_string()).map(|k| k as u8), Some(code));
+        }
+        assert!(BinOpKind::from_code(BinOpKind::ALL.len() as u8).is_none());
+    }
+
+    #[test]
+    fn integer_division() {
+        let int = |n: isize| ObjectData::Integer(n);
+        let data = |res: Result<_, ProgramErrorKind>| res.map(|obj: crate::object::Object| obj.data);
+        assert_eq!(data(div(int(-7), int(2))).ok(), Some(int(-3)));
+        assert_eq!(data(floor_div(int(-7), int(2))).ok(), Some(int(-4)));
+        assert_eq!(data(floor_div(int(7), int(-7))).ok(), Some(int(-1)));
+        assert_eq!(data(modulus(int(-7), int(2))).ok(), Some(int(-1)));
+        for op in [div, floor_div, modulus] {
+            assert!(matches!(op(int(1), int(0)), Err(ProgramErrorKind::DivisionByZero)));
+            assert!(matches!(op(int(isize::MIN), int(-1)), Err(ProgramErrorKind::Overflow(..))));
+        }
+    }
+
+    #[test]
+    fn bools_compare() {
+        let b = |v: bool| ObjectData::Bool(v);
+        assert_eq!(eq(b(true), b(true)).map(|obj| obj.data).ok(), Some(b(true)));
+        assert_eq!(not_eq(b(true), b(false)).map(|obj| obj.data).ok(), Some(b(true)));
+        assert!(not_eq(b(true), ObjectData::Integer(1)).is_err());
+    }
+
+    #[test]
+    fn integer_powers_and_roots() {
+        let int = |n: isize| ObjectData::Integer(n);
+        let data = |res: Result<_, ProgramErrorKind>| res.map(|obj: crate::object::Object| obj.data);
+        assert_eq!(data(pow(int(-2), int(3))).ok(), Some(int(-8)));
+        assert!(matches!(pow(int(2), int(-1)), Err(ProgramErrorKind::IntegerToUnsigned)));
+        assert!(matches!(pow(int(2), int(64)), Err(ProgramErrorKind::Overflow(BinOpKind::Power, ..))));
+        assert!(matches!(pow(int(2), int(isize::MAX)), Err(ProgramErrorKind::Overflow(..))));
+        assert_eq!(data(root(int(27), int(3))).ok(), Some(int(3)));
+        assert_eq!(data(root(int(26), int(3))).ok(), Some(int(2)));
+        assert_eq!(data(root(int(-27), int(3))).ok(), Some(int(-3)));
+        assert_eq!(data(root(int(isize::MAX), int(1))).ok(), Some(int(isize::MAX)));
+        assert_eq!(data(root(int(isize::MIN), int(1))).ok(), Some(int(isize::MIN)));
+        assert!(matches!(root(int(4), int(0)), Err(ProgramErrorKind::DivisionByZero)));
+        assert!(matches!(root(int(-4), int(2)), Err(ProgramErrorKind::IntegerToUnsigned)));
     }
 }
@@ -1,4 +1,4 @@
-use std::fmt::Display;
+use std::{fmt::Display, io::Write};
 
 use crate::object::{Object, ObjectData};
 
@@ -10,10 +10,12 @@ pub enum BuiltIn {
 }
 
 impl BuiltIn {
-    pub fn call(&self, arg: Object) -> Option<Object> {
+    /// `println` writes to `out`, the VM's output.
+    pub fn call(&self, arg: Object, out: &mut dyn Write) -> Option<Object> {
         match self {
             BuiltIn::PrintLn => {
-                println!("{arg}");
+                // like `println!`, except a closed output isn't worth a panic
+                let _ = writeln!(out, "{arg}");
 
                 None
             }
@@ -31,23 +33,36 @@ impl Display for BuiltIn {
     }
 }
 
+impl BuiltIn {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "println" => Some(BuiltIn::PrintLn),
+            "sqrt" => Some(BuiltIn::Sqrt),
+            _ => None,
+        }
+    }
+}
+
 impl From<&str> for BuiltIn {
     fn from(value: &str) -> Self {
-        match value {
-            "println" => BuiltIn::PrintLn,
-            "sqrt" => BuiltIn::Sqrt,
-            _ => panic!("No such builtin '{}'", value),
+        BuiltIn::parse(value).unwrap_or_else(|| panic!("No such builtin '{}'", value))
+    }
+}
+
+impl BuiltIn {
+    /// The builtin stored as `code` in Bytecode.
+    pub fn from_code(code: u8) -> Option<Self> {
+        match code {
+            0 => Some(BuiltIn::PrintLn),
+            1 => Some(BuiltIn::Sqrt),
+            _ => None,
         }
     }
 }
 
 impl From<u8> for BuiltIn {
     fn from(value: u8) -> Self {
-        match value {
-            0 => BuiltIn::PrintLn,
-            1 => BuiltIn::Sqrt,
-            _ => panic!(),
-        }
+        BuiltIn::from_code(value).unwrap_or_else(|| panic!("no builtin {}", value))
     }
 }
 
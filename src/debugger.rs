@@ -0,0 +1,191 @@
+use std::io::{self, BufRead, Write};
+
+use jed::{bytes_to_string, Frame, FrameKind, Hook, Operation, VM};
+
+const HELP: &str = "\
+  step, s             run the next instruction
+  next, n             run until the next instruction in this function, stepping over calls
+  finish, f           run until the current function returns
+  continue, c         run until a breakpoint
+  break, b PC|FUNC    stop before the instruction at PC, or at the start of FUNC
+  delete, d PC|FUNC   remove a breakpoint
+  breaks              list the breakpoints
+  list, l             show the instructions around the current one
+  frames, bt          list the frames on the call stack
+  locals [N]          print the locals of frame N (0 is the innermost, default)
+  stack               print the object stack
+  temp                print the temp register
+  quit, q             stop the program";
+
+enum Mode {
+    Step,
+    // stop once the call depth is at most this
+    Next(usize),
+    // stop once the call depth is below this
+    Finish(usize),
+    Continue,
+}
+
+/// Interactive debugger, drives the VM from its `Hook` by blocking on stdin
+/// before every instruction it stops at.
+pub struct Debugger {
+    breakpoints: Vec<usize>,
+    mode: Mode,
+}
+
+impl Debugger {
+    pub fn new() -> Self {
+        Debugger {
+            breakpoints: vec![],
+            mode: Mode::Step,
+        }
+    }
+
+    fn should_stop(&self, vm: &VM) -> bool {
+        if self.breakpoints.contains(&vm.counter) {
+            return true;
+        }
+        match self.mode {
+            Mode::Step => true,
+            Mode::Next(depth) => call_depth(vm) <= depth,
+            Mode::Finish(depth) => call_depth(vm) < depth,
+            Mode::Continue => false,
+        }
+    }
+
+    fn prompt(&mut self, vm: &VM) {
+        let stdin = io::stdin();
+        loop {
+            print!("(jdb) ");
+            let _ = io::stdout().flush();
+            let mut line = String::new();
+            match stdin.lock().read_line(&mut line) {
+                Ok(0) | Err(_) => std::process::exit(0),
+                Ok(_) => {}
+            }
+            let mut words = line.split_whitespace();
+            let command = words.next().unwrap_or("step");
+            let arg = words.next();
+
+            match command {
+                "step" | "s" => {
+                    self.mode = Mode::Step;
+                    return;
+                }
+                "next" | "n" => {
+                    self.mode = Mode::Next(call_depth(vm));
+                    return;
+                }
+                "finish" | "f" => {
+                    self.mode = Mode::Finish(call_depth(vm));
+                    return;
+                }
+                "continue" | "c" => {
+                    self.mode = Mode::Continue;
+                    return;
+                }
+                "break" | "b" => match arg.and_then(|a| resolve_location(vm, a)) {
+                    Some(pc) => {
+                        if !self.breakpoints.contains(&pc) {
+                            self.breakpoints.push(pc);
+                        }
+                        println!("breakpoint at {pc}  {}", vm.program.get_op(pc));
+                    }
+                    None => println!("no such pc or function"),
+                },
+                "delete" | "d" => match arg.and_then(|a| resolve_location(vm, a)) {
+                    Some(pc) => self.breakpoints.retain(|b| *b != pc),
+                    None => println!("no such pc or function"),
+                },
+                "breaks" => {
+                    for pc in &self.breakpoints {
+                        println!("{pc}  {}", vm.program.get_op(*pc));
+                    }
+                }
+                "list" | "l" => println!("{}", vm.current_span),
+                "frames" | "bt" => {
+                    for (idx, frame) in vm.call_stack().iter().rev().enumerate() {
+                        println!("#{idx}  {}", describe_frame(vm, frame));
+                    }
+                }
+                "locals" => {
+                    let idx = arg.and_then(|a| a.parse::<usize>().ok()).unwrap_or(0);
+                    match vm.call_stack().iter().rev().nth(idx) {
+                        Some(frame) => {
+                            println!("#{idx}  {}", describe_frame(vm, frame));
+                            for (name, obj) in &frame.locals {
+                                println!("  {} = {:?}", bytes_to_string(name), obj.data);
+                            }
+                        }
+                        None => println!("no frame #{idx}"),
+                    }
+                }
+                "stack" => {
+                    for (idx, obj) in vm.obj_stack().iter().enumerate().rev() {
+                        println!("{idx:>4}  {:?}", obj.data);
+                    }
+                }
+                "temp" => match vm.temp {
+                    Some(obj) => println!("{:?}", obj.data),
+                    None => println!("(empty)"),
+                },
+                "quit" | "q" => std::process::exit(0),
+                "help" | "h" => println!("{HELP}"),
+                _ => println!("unknown command '{command}', try 'help'"),
+            }
+        }
+    }
+}
+
+impl Hook for Debugger {
+    fn before_op(&mut self, vm: &VM, _op: Operation) {
+        if !self.should_stop(vm) {
+            return;
+        }
+        let func = vm
+            .program
+            .func_containing(vm.counter)
+            .map_or("?".to_owned(), bytes_to_string);
+        println!("in {func}:\n{}", vm.current_span);
+        self.prompt(vm);
+    }
+}
+
+/// Number of function calls on the call stack, loops and `do_if` blocks don't count.
+fn call_depth(vm: &VM) -> usize {
+    vm.call_stack()
+        .iter()
+        .filter(|f| matches!(f.kind, FrameKind::Call | FrameKind::Main))
+        .count()
+}
+
+fn resolve_location(vm: &VM, location: &str) -> Option<usize> {
+    match location.parse::<usize>() {
+        Ok(pc) if pc < vm.program.instructions.len() => Some(pc),
+        Ok(_) => None,
+        Err(_) => vm
+            .program
+            .funcs
+            .get(location.as_bytes())
+            .map(|(idx, _)| *idx),
+    }
+}
+
+fn describe_frame(vm: &VM, frame: &Frame) -> String {
+    match frame.kind {
+        FrameKind::Call => {
+            let name = vm
+                .program
+                .func_name(frame.function())
+                .map_or("?".to_owned(), bytes_to_string);
+            let args: Vec<String> = frame.args().iter().map(|o| format!("{o}")).collect();
+            format!(
+                "call {name}({}), returns to {}",
+                args.join(", "),
+                frame.return_address
+            )
+        }
+        FrameKind::Main => "main".to_owned(),
+        ref kind => format!("{kind:?}, started at {}", frame.return_address),
+    }
+}
@@ -24,6 +24,12 @@ pub enum ProgramErrorKind {
     IterPrevious,    // index, length
     TodoError,
     DoneAddress,
+    JumpAddress,
+    NoLoop,
+    DivisionByZero,
+    NoMain,
+    CallStackOverflow,
+    ObjectStackOverflow,
 }
 
 impl Display for ProgramErrorKind {
@@ -71,11 +77,51 @@ impl Display for ProgramErrorKind {
                 write!(f, "can not get next in a list of {} length", len)
             }
             ProgramErrorKind::IterPrevious => write!(f, "can not get previous",),
-            ProgramErrorKind::ConstantExists(bytes) => todo!(),
+            ProgramErrorKind::ConstantExists(items) => write!(
+                f,
+                "constant '{}' does not exist",
+                utils::bytes_to_string(items)
+            ),
             ProgramErrorKind::TodoError => {
                 write!(f, "there is an error here, but im not sure what it is")
             }
             ProgramErrorKind::DoneAddress => write!(f, "somehow there's no done address to go to"),
+            ProgramErrorKind::JumpAddress => write!(f, "somehow there's no label to jump to"),
+            ProgramErrorKind::NoLoop => write!(f, "'break' or 'continue' outside of a loop"),
+            ProgramErrorKind::DivisionByZero => write!(f, "attempt to divide by zero"),
+            ProgramErrorKind::NoMain => write!(f, "there's no 'main' function to run"),
+            ProgramErrorKind::CallStackOverflow => write!(f, "call stack overflow"),
+            ProgramErrorKind::ObjectStackOverflow => write!(f, "object stack overflow"),
+        }
+    }
+}
+
+impl ProgramErrorKind {
+    /// Exit code `jed` uses when a program fails with this kind of error,
+    /// 1 is left for everything that isn't a runtime error.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ProgramErrorKind::StackError(_) => 10,
+            ProgramErrorKind::BinopError(_, _, _) => 11,
+            ProgramErrorKind::FunctionExists(_) => 12,
+            ProgramErrorKind::VariableExists(_) => 13,
+            ProgramErrorKind::TempPush => 14,
+            ProgramErrorKind::TypeError(_, _) => 15,
+            ProgramErrorKind::ParsingError(_) => 16,
+            ProgramErrorKind::Overflow(_, _, _) => 17,
+            ProgramErrorKind::IntegerToUnsigned => 18,
+            ProgramErrorKind::ListIndexError(_, _) => 19,
+            ProgramErrorKind::ConstantExists(_) => 20,
+            ProgramErrorKind::IterNext(_) => 21,
+            ProgramErrorKind::IterPrevious => 22,
+            ProgramErrorKind::TodoError => 23,
+            ProgramErrorKind::DoneAddress => 24,
+            ProgramErrorKind::JumpAddress => 25,
+            ProgramErrorKind::NoLoop => 26,
+            ProgramErrorKind::DivisionByZero => 27,
+            ProgramErrorKind::NoMain => 28,
+            ProgramErrorKind::CallStackOverflow => 29,
+            ProgramErrorKind::ObjectStackOverflow => 30,
         }
     }
 }
@@ -85,6 +131,89 @@ pub struct ProgramError(pub ProgramErrorKind, pub Span);
 
 impl Display for ProgramError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}\ndetail:\n {}", self.1, self.0)
+        match self.1.source {
+            Some(source) => write!(f, "{}\ndetail:\n {}", source, self.0),
+            None => write!(f, "{}\ndetail:\n {}", self.1, self.0),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum ParseErrorKind {
+    UnknownOperation(String),
+    Unsupported(String),
+    UnknownBuiltIn(String),
+    UnknownBinOp(String),
+    BadArity(String),
+    BadCount(String, String), // op, argument
+    UndefinedFunction(String),
+    UnknownName(String),
+    UnclosedBlock(String),
+    UnmatchedClose(String),
+    UnknownLabel(String),
+    DuplicateLabel(String),
+    MisplacedBranch(String, String), // branch, block it belongs in
+    MissingCheck,
+    OutsideLoop(String),
+    InvalidLiteral(String),
+    NotBytecode,
+    BadBytecode(String),
+    NotUtf8,
+    Io(String),
+}
+
+impl Display for ParseErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseErrorKind::UnknownOperation(op) => write!(f, "'{op}' is not a valid operation"),
+            ParseErrorKind::Unsupported(op) => write!(f, "'{op}' is not supported yet"),
+            ParseErrorKind::UnknownBuiltIn(name) => write!(f, "no such builtin '{name}'"),
+            ParseErrorKind::UnknownBinOp(name) => write!(f, "no such binary operator '{name}'"),
+            ParseErrorKind::BadArity(arg) => {
+                write!(f, "'{arg}' is not a function name followed by its arity")
+            }
+            ParseErrorKind::BadCount(op, arg) => write!(f, "'{arg}' is not a count for '{op}'"),
+            ParseErrorKind::UndefinedFunction(name) => {
+                write!(f, "call to nonexistent function '{name}'")
+            }
+            ParseErrorKind::UnknownName(name) => {
+                write!(f, "'{name}' is never stored in this function")
+            }
+            ParseErrorKind::UnclosedBlock(op) => write!(f, "'{op}' block is never closed"),
+            ParseErrorKind::UnmatchedClose(op) => write!(f, "'{op}' without a block to close"),
+            ParseErrorKind::UnknownLabel(name) => {
+                write!(f, "no label '{name}' in the same block as the jump")
+            }
+            ParseErrorKind::DuplicateLabel(name) => {
+                write!(f, "label '{name}' is already defined in this block")
+            }
+            ParseErrorKind::MisplacedBranch(op, opener) => {
+                write!(f, "'{op}' only goes directly inside a {opener} block, once")
+            }
+            ParseErrorKind::MissingCheck => write!(f, "'do_while' block has no 'check'"),
+            ParseErrorKind::OutsideLoop(op) => write!(f, "'{op}' has to be inside a loop body"),
+            ParseErrorKind::InvalidLiteral(reason) => write!(f, "{reason}"),
+            ParseErrorKind::NotBytecode => write!(f, "not a jed Bytecode file"),
+            ParseErrorKind::BadBytecode(reason) => write!(f, "{reason}"),
+            ParseErrorKind::NotUtf8 => write!(f, "string Jed Bytecode has to be UTF-8"),
+            ParseErrorKind::Io(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+/// A problem in a program and where it is: the line (starting at 1) for string
+/// Jed Bytecode, the instruction index for Bytecode.
+#[derive(Debug, Clone)]
+pub struct ParseError(pub ParseErrorKind, pub usize);
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            ParseErrorKind::NotBytecode | ParseErrorKind::NotUtf8 | ParseErrorKind::Io(_) => {
+                write!(f, "{}", self.0)
+            }
+            ParseErrorKind::BadBytecode(_) => write!(f, "instruction {}: {}", self.1, self.0),
+            _ => write!(f, "line {}: {}", self.1, self.0),
+        }
     }
 }
@@ -0,0 +1,87 @@
+use jed::{Operation, Program};
+
+const INDENT: &str = "\t";
+
+/// Re-emits string Jed Bytecode with one tab of indentation per open block.
+///
+/// Lines are split with `Program::split_line`, so every instruction keeps the
+/// exact argument the parser saw. Comments are kept at the indentation of the
+/// code around them (or at the end of their instruction), runs of blank lines
+/// are squashed into one.
+pub fn format(text: &str) -> String {
+    let mut out = String::new();
+    let mut depth: usize = 0;
+    let mut pending_blank = false;
+
+    for line in text.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            pending_blank = !out.is_empty();
+            continue;
+        }
+        if pending_blank {
+            out.push('\n');
+            pending_blank = false;
+        }
+
+        let Some((op, arg)) = Program::split_line(line) else {
+            // comment
+            out.push_str(&INDENT.repeat(depth));
+            out.push_str(trimmed);
+            out.push('\n');
+            continue;
+        };
+
+        if Operation::closes_block(op) {
+            depth = depth.saturating_sub(1);
+        }
+        let branch = usize::from(Operation::branches_block(op));
+        out.push_str(&INDENT.repeat(depth.saturating_sub(branch)));
+        out.push_str(op);
+        if !arg.is_empty() {
+            out.push(' ');
+            out.push_str(&arg);
+        }
+        if let (_, Some(comment)) = Program::split_comment(line) {
+            out.push(' ');
+            out.push_str(comment.trim_end());
+        }
+        out.push('\n');
+        if Operation::opens_block(op) {
+            depth += 1;
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::format;
+
+    #[test]
+    fn indents_nested_blocks() {
+        let text = "# counts\n\n\nfunc main 0\n  push_lit 3   # times\n do_for\n\t\t\tpush_lit \"#  hi\"\n# say it\ncall_builtin println\n    done\nexit\n\n";
+        let formatted = format(text);
+        assert_eq!(
+            formatted,
+            "# counts\n\nfunc main 0\n\tpush_lit 3 # times\n\tdo_for\n\t\tpush_lit \"#  hi\"\n\t\t# say it\n\t\tcall_builtin println\n\tdone\nexit\n"
+        );
+        assert_eq!(format(&formatted), formatted);
+    }
+
+    #[test]
+    fn else_sits_with_its_do_if() {
+        let text = "do_if
+push_lit 1
+else
+push_lit 2
+done
+";
+        assert_eq!(format(text), "do_if
+	push_lit 1
+else
+	push_lit 2
+done
+");
+    }
+}
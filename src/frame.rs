@@ -7,6 +7,7 @@ pub enum FrameKind {
     DoForLoop,
     IterateLoop,
     DoForInLoop,
+    DoWhileLoop,
     DoIfBlock,
     Call,
     Main,
@@ -17,8 +18,21 @@ pub struct Frame {
     // String -> Literal
     pub locals: BTreeMap<&'static [u8], &'static Object>,
     pub return_address: usize,
-    pub memo_key: MemoKey,
+    pub(crate) memo_key: MemoKey,
     pub kind: FrameKind,
+    // object stack height when a loop's current pass started, `break` and
+    // `continue` drop whatever the pass left above it
+    pub stack_height: usize,
+}
+
+impl FrameKind {
+    /// Whether `break` and `continue` stop at a frame of this kind.
+    pub fn is_loop(&self) -> bool {
+        matches!(
+            self,
+            FrameKind::DoForLoop | FrameKind::IterateLoop | FrameKind::DoForInLoop | FrameKind::DoWhileLoop
+        )
+    }
 }
 
 impl Frame {
@@ -28,9 +42,20 @@ impl Frame {
             locals: BTreeMap::new(),
             return_address,
             kind,
+            stack_height: 0,
         }
     }
 
+    /// Where the called function starts, 0 for frames that aren't calls.
+    pub fn function(&self) -> usize {
+        self.memo_key.0
+    }
+
+    /// What the function was called with.
+    pub fn args(&self) -> &'static [Object] {
+        self.memo_key.1
+    }
+
     pub fn add_local(&mut self, name: &'static [u8], obj: &'static Object) {
         // if self.locals.contains_key(name) {
         //     panic!("{} has already been declared");
@@ -0,0 +1,267 @@
+use std::{
+    cell::RefCell,
+    fmt::Write as _,
+    fs,
+    io::{self, Read},
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use jed::{MAGIC_NUMBER, VM};
+
+const EXPECT: &str = "# expect:";
+const ARGS: &str = "# args:";
+const CONTEXT_LINES: usize = 3;
+
+enum Outcome {
+    Pass,
+    Fail(String),
+    Skip,
+}
+
+/// Runs every `.jed`/`.jbc` file in `path` (or just `path` if it is a file) and
+/// compares what it prints against its expected output.
+///
+/// The expected output is either a `# expect:` comment block inside a `.jed` file:
+/// ```text
+/// # expect:
+/// # Hello world!
+/// ```
+/// (or the single line form `# expect: Hello world!`), or a sibling `.out` file.
+/// Files without either are skipped. A `# args: a b c` line gives `main` its
+/// arguments. Only what `println` writes is compared, through the VM's output.
+/// Returns whether every file passed.
+pub fn run(path: &Path, output: &Path) -> io::Result<bool> {
+    let files = if path.is_dir() {
+        let mut files: Vec<PathBuf> = fs::read_dir(path)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|p| matches!(p.extension().and_then(|e| e.to_str()), Some("jed" | "jbc")))
+            .collect();
+        files.sort();
+        files
+    } else {
+        vec![path.to_path_buf()]
+    };
+
+    let (mut passed, mut failed, mut skipped) = (0, 0, 0);
+    for file in &files {
+        match test_file(file, output)? {
+            Outcome::Pass => {
+                passed += 1;
+                println!("PASS {}", file.display());
+            }
+            Outcome::Fail(reason) => {
+                failed += 1;
+                println!("FAIL {}\n{reason}", file.display());
+            }
+            Outcome::Skip => {
+                skipped += 1;
+                println!("SKIP {} (no expected output)", file.display());
+            }
+        }
+    }
+    println!("\n{passed} passed, {failed} failed, {skipped} skipped");
+    Ok(failed == 0)
+}
+
+/// The VM's output, kept where the runner can read it back after the run.
+#[derive(Clone, Default)]
+struct Capture(Rc<RefCell<Vec<u8>>>);
+
+impl io::Write for Capture {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn test_file(file: &Path, output: &Path) -> io::Result<Outcome> {
+    let Some(Expected { output: expected, args }) = expected_output(file)? else {
+        return Ok(Outcome::Skip);
+    };
+
+    let mut reason = String::new();
+    let program = match super::load_program(file, output)? {
+        Ok(program) => program,
+        Err(e) => return Ok(Outcome::Fail(format!("  {e}\n"))),
+    };
+    let capture = Capture::default();
+    let mut vm = VM::new(program, false);
+    vm.output = Box::new(capture.clone());
+
+    // a panicking program only fails its own test, the message goes in the reason
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(|_| {}));
+    let result = panic::catch_unwind(AssertUnwindSafe(|| vm.run_with_args(&args)));
+    panic::set_hook(hook);
+    match result {
+        Ok(Ok(_)) => {}
+        Ok(Err(e)) => {
+            let _ = writeln!(reason, "  exited with {}", e.0.exit_code());
+            for line in e.to_string().lines() {
+                let _ = writeln!(reason, "  | {line}");
+            }
+        }
+        Err(payload) => {
+            let message = payload
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_default();
+            let _ = writeln!(reason, "  panicked: {message}");
+        }
+    }
+
+    let actual = String::from_utf8_lossy(&capture.0.borrow()).into_owned();
+    let expected_lines: Vec<&str> = expected.lines().collect();
+    let actual_lines: Vec<&str> = actual.lines().collect();
+    if expected_lines != actual_lines {
+        reason.push_str(&unified_diff(&expected_lines, &actual_lines));
+    }
+
+    if reason.is_empty() {
+        Ok(Outcome::Pass)
+    } else {
+        Ok(Outcome::Fail(reason))
+    }
+}
+
+/// What a file should print, along with the arguments its `main` gets.
+struct Expected {
+    output: String,
+    args: Vec<String>,
+}
+
+fn expected_output(file: &Path) -> io::Result<Option<Expected>> {
+    let mut contents = vec![];
+    fs::File::open(file)?.read_to_end(&mut contents)?;
+
+    let mut args = vec![];
+    if !contents.starts_with(MAGIC_NUMBER) {
+        let text = String::from_utf8_lossy(&contents);
+        let mut expected: Option<String> = None;
+        let mut in_block = false;
+        for line in text.lines().map(str::trim) {
+            if let Some(rest) = line.strip_prefix(ARGS) {
+                args = rest.split_whitespace().map(str::to_owned).collect();
+                in_block = false;
+            } else if let Some(rest) = line.strip_prefix(EXPECT) {
+                let out = expected.get_or_insert_with(String::new);
+                if rest.trim().is_empty() {
+                    in_block = true;
+                } else {
+                    out.push_str(rest.strip_prefix(' ').unwrap_or(rest));
+                    out.push('\n');
+                }
+            } else if in_block && line.starts_with('#') {
+                let rest = &line[1..];
+                let out = expected.get_or_insert_with(String::new);
+                out.push_str(rest.strip_prefix(' ').unwrap_or(rest));
+                out.push('\n');
+            } else {
+                in_block = false;
+            }
+        }
+        if let Some(output) = expected {
+            return Ok(Some(Expected { output, args }));
+        }
+    }
+
+    match fs::read_to_string(file.with_extension("out")) {
+        Ok(output) => Ok(Some(Expected { output, args })),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Line based unified diff of `expected` against `actual`, built from the
+/// longest common subsequence of the two.
+fn unified_diff(expected: &[&str], actual: &[&str]) -> String {
+    // lcs[i][j] is the length of the lcs of expected[i..] and actual[j..]
+    let mut lcs = vec![vec![0usize; actual.len() + 1]; expected.len() + 1];
+    for i in (0..expected.len()).rev() {
+        for j in (0..actual.len()).rev() {
+            lcs[i][j] = if expected[i] == actual[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    // (marker, line, index in expected, index in actual)
+    let mut edits: Vec<(char, &str, usize, usize)> = vec![];
+    let (mut i, mut j) = (0, 0);
+    while i < expected.len() || j < actual.len() {
+        if i < expected.len() && j < actual.len() && expected[i] == actual[j] {
+            edits.push((' ', expected[i], i, j));
+            i += 1;
+            j += 1;
+        } else if i < expected.len() && (j == actual.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            edits.push(('-', expected[i], i, j));
+            i += 1;
+        } else {
+            edits.push(('+', actual[j], i, j));
+            j += 1;
+        }
+    }
+
+    let mut out = String::from("--- expected\n+++ actual\n");
+    let changes: Vec<usize> = (0..edits.len()).filter(|&n| edits[n].0 != ' ').collect();
+    let mut idx = 0;
+    while idx < changes.len() {
+        // grow the hunk while the next change is close enough to share context
+        let start = changes[idx].saturating_sub(CONTEXT_LINES);
+        let mut end = changes[idx];
+        while idx + 1 < changes.len() && changes[idx + 1] <= end + 2 * CONTEXT_LINES + 1 {
+            idx += 1;
+            end = changes[idx];
+        }
+        let end = (end + CONTEXT_LINES + 1).min(edits.len());
+        idx += 1;
+
+        let hunk = &edits[start..end];
+        let old_len = hunk.iter().filter(|e| e.0 != '+').count();
+        let new_len = hunk.iter().filter(|e| e.0 != '-').count();
+        let _ = writeln!(
+            out,
+            "@@ -{},{old_len} +{},{new_len} @@",
+            hunk[0].2 + 1,
+            hunk[0].3 + 1
+        );
+        for (marker, line, _, _) in hunk {
+            let _ = writeln!(out, "{marker}{line}");
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::unified_diff;
+
+    #[test]
+    fn diff_of_changed_line() {
+        let expected = ["1", "2", "3"];
+        let actual = ["1", "two", "3"];
+        assert_eq!(
+            unified_diff(&expected, &actual),
+            "--- expected\n+++ actual\n@@ -1,3 +1,3 @@\n 1\n-2\n+two\n 3\n"
+        );
+    }
+
+    #[test]
+    fn diff_of_missing_tail() {
+        let expected = ["a", "b"];
+        let actual = ["a"];
+        assert_eq!(
+            unified_diff(&expected, &actual),
+            "--- expected\n+++ actual\n@@ -1,2 +1,1 @@\n a\n-b\n"
+        );
+    }
+}
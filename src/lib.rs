@@ -0,0 +1,43 @@
+//! Jed Bytecode interpreter.
+//!
+//! Parse a `Program` (from string Jed Bytecode, `.jbc` Bytecode, raw bytes or
+//! any `Read`), hand it to a `VM` and `run` it.
+//! ```
+//! let program = jed::Program::from_string("func main 0\n\tpush_lit 1\ndone\n")
+//!     .expect("parses");
+//! let mut vm = jed::VM::new(program, false);
+//! let result = vm.run().expect("runs");
+//! assert_eq!(result.map(|obj| obj.to_string()), Some("1".to_owned()));
+//! ```
+//! A `Hook` gets to look at the VM before every instruction, which is how the
+//! `jed` debugger and profiler are built.
+mod arena;
+mod binops;
+mod builtin;
+mod error;
+mod frame;
+mod indexmap;
+mod literal;
+mod map;
+mod modules;
+mod object;
+mod operation;
+mod program;
+mod span;
+mod stack;
+mod utils;
+mod vm;
+
+pub use binops::BinOpKind;
+pub use builtin::BuiltIn;
+pub use error::{ParseError, ParseErrorKind, ProgramError, ProgramErrorKind};
+pub use frame::{Frame, FrameKind};
+pub use object::{Object, ObjectData, ObjectKind};
+pub use operation::Operation;
+pub use program::Program;
+pub use span::Span;
+pub use utils::bytes_to_string;
+pub use vm::{Hook, Unwind, VM};
+
+/// First bytes of every Bytecode (`.jbc`) file.
+pub const MAGIC_NUMBER: &[u8] = "jed".as_bytes();
@@ -0,0 +1,307 @@
+//! The literals `push_lit` takes, parsed once when a program is loaded.
+//!
+//! A `push_lit` argument that reads like a name isn't a literal, it's looked
+//! up among the consts when it runs. List literals are kept as a tree and
+//! built into a fresh `List` on every push, since lists can be changed.
+
+use std::convert::TryFrom;
+
+use crate::{
+    object::{Object, ObjectData, ObjectKind},
+    program::Program,
+    utils,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Literal {
+    Value(Object),
+    List(Vec<Literal>),
+}
+
+/// The value of `text`, `None` if it's a const name rather than a literal.
+pub fn parse(program: &mut Program, text: &str) -> Result<Option<Literal>, String> {
+    if text.starts_with('[') {
+        let (list, rest) = list(program, text)?;
+        if !rest.trim().is_empty() {
+            return Err(format!("unexpected {} after list {text}", rest.trim()));
+        }
+        return Ok(Some(list));
+    }
+    if is_name(text) && !matches!(text, "true" | "false" | "Nil") {
+        return Ok(None);
+    }
+    value(program, text).map(|object| Some(Literal::Value(object)))
+}
+
+/// A list at the start of `text`, along with whatever follows it.
+fn list<'a>(program: &mut Program, text: &'a str) -> Result<(Literal, &'a str), String> {
+    let unclosed = || format!("unclosed list {text}");
+    let mut rest = text[1..].trim_start();
+    let mut items = vec![];
+    if let Some(after) = rest.strip_prefix(']') {
+        return Ok((Literal::List(items), after));
+    }
+    loop {
+        let (item, after) = element(program, rest)?;
+        items.push(item);
+        let after = after.trim_start();
+        if let Some(after) = after.strip_prefix(',') {
+            rest = after.trim_start();
+        } else if let Some(after) = after.strip_prefix(']') {
+            return Ok((Literal::List(items), after));
+        } else if after.is_empty() {
+            return Err(unclosed());
+        } else {
+            return Err(format!("expected , or ] before {after}"));
+        }
+    }
+}
+
+/// One item of a list, which ends at the next `,` or `]` outside a string.
+fn element<'a>(program: &mut Program, text: &'a str) -> Result<(Literal, &'a str), String> {
+    if text.starts_with('[') {
+        return list(program, text);
+    }
+    let end = if text.starts_with('"') {
+        string_end(text).ok_or_else(|| format!("unterminated string {text}"))?
+    } else {
+        text.find([',', ']']).unwrap_or(text.len())
+    };
+    let (item, rest) = text.split_at(end);
+    let item = item.trim();
+    if item.is_empty() {
+        return Err("empty list item".to_owned());
+    }
+    if is_name(item) && !matches!(item, "true" | "false" | "Nil") {
+        return Err(format!("{item} is a name, lists only hold literals"));
+    }
+    Ok((Literal::Value(value(program, item)?), rest))
+}
+
+/// Byte index just past the quote that closes the string `text` starts with.
+fn string_end(text: &str) -> Option<usize> {
+    let mut escaped = false;
+    for (i, c) in text.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => return Some(i + 1),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// A string, bool, `Nil` or number.
+fn value(program: &mut Program, text: &str) -> Result<Object, String> {
+    if let Some(value) = utils::string_literal(text) {
+        let bytes = program.register(value?);
+        return Ok(Object {
+            kind: ObjectKind::String,
+            data: ObjectData::String(bytes),
+        });
+    }
+    match text {
+        "true" | "false" => Ok(Object {
+            kind: ObjectKind::Bool,
+            data: ObjectData::Bool(text == "true"),
+        }),
+        "Nil" => Ok(Object::nil()),
+        _ => number(text),
+    }
+}
+
+fn is_name(text: &str) -> bool {
+    text.chars()
+        .next()
+        .is_some_and(|c| c.is_alphabetic() || c == '_')
+}
+
+/// Integers take a `-` sign, `0x`/`0b` prefixes and `_` between digits,
+/// anything with a `.` or an exponent is a float.
+fn number(text: &str) -> Result<Object, String> {
+    let invalid = || format!("invalid number {text}");
+    let (negative, unsigned) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text),
+    };
+    let lower = unsigned.to_ascii_lowercase();
+    let (radix, digits) = if let Some(hex) = lower.strip_prefix("0x") {
+        (16, hex)
+    } else if let Some(bin) = lower.strip_prefix("0b") {
+        (2, bin)
+    } else if lower.contains(['.', 'e']) {
+        return float(negative, &lower).map_err(|reason| match reason {
+            Some(reason) => format!("{text} can't be a float, {reason}"),
+            None => invalid(),
+        });
+    } else {
+        (10, lower.as_str())
+    };
+    let digits = separated_digits(digits, radix).ok_or_else(invalid)?;
+    // the sign goes in before parsing so isize::MIN fits
+    let signed = if negative {
+        format!("-{digits}")
+    } else {
+        digits
+    };
+    match isize::from_str_radix(&signed, radix) {
+        Ok(value) => Ok(Object::from(value)),
+        Err(_) => Err(format!("{text} doesn't fit in an integer")),
+    }
+}
+
+/// `digits` without its `_` separators, which may only sit between digits.
+fn separated_digits(digits: &str, radix: u32) -> Option<String> {
+    if digits.is_empty() || digits.starts_with('_') || digits.ends_with('_') {
+        return None;
+    }
+    if !digits.chars().all(|c| c == '_' || c.is_digit(radix)) {
+        return None;
+    }
+    Some(digits.replace('_', ""))
+}
+
+/// Floats are stored as whole and fractional digits, so the exponent just
+/// moves the decimal point before they're split. Fails with the reason when
+/// the number is fine but has no such float, with `None` when it's malformed.
+fn float(negative: bool, text: &str) -> Result<Object, Option<&'static str>> {
+    let (mantissa, exponent) = match text.split_once('e') {
+        Some((mantissa, exponent)) => {
+            let exponent = exponent.strip_prefix('+').unwrap_or(exponent);
+            let (exp_negative, exp_digits) = match exponent.strip_prefix('-') {
+                Some(rest) => (true, rest),
+                None => (false, exponent),
+            };
+            let exp: isize = separated_digits(exp_digits, 10)
+                .ok_or(None)?
+                .parse()
+                .map_err(|_| None)?;
+            (mantissa, if exp_negative { -exp } else { exp })
+        }
+        None => (text, 0),
+    };
+    let (whole, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let whole = separated_digits(whole, 10).ok_or(None)?;
+    let fraction = if fraction.is_empty() {
+        String::new()
+    } else {
+        separated_digits(fraction, 10).ok_or(None)?
+    };
+
+    // past this the digits can't fit an i32 or u32 anyway
+    if exponent.unsigned_abs() > 40 {
+        return Err(None);
+    }
+    let mut digits = whole.clone() + &fraction;
+    let point = isize::try_from(whole.len()).map_err(|_| None)? + exponent;
+    if point < 0 {
+        digits.insert_str(0, &"0".repeat(point.unsigned_abs()));
+    }
+    let point = usize::try_from(point).unwrap_or(0);
+    if point > digits.len() {
+        digits.push_str(&"0".repeat(point - digits.len()));
+    }
+    let (whole, fraction) = digits.split_at(point);
+
+    let whole: i32 = if whole.is_empty() {
+        0
+    } else {
+        whole.parse().map_err(|_| None)?
+    };
+    let fraction = fraction.trim_end_matches('0');
+    // the fractional digits are kept as a number, which has no leading zeros
+    if fraction.starts_with('0') {
+        return Err(Some("its fraction starts with a 0"));
+    }
+    let prec: u32 = if fraction.is_empty() {
+        0
+    } else {
+        fraction.parse().map_err(|_| None)?
+    };
+    // the sign lives on the whole part, so -0.5 has nowhere to put it
+    if negative && whole == 0 && prec != 0 {
+        return Err(Some("it's negative with a whole part of 0"));
+    }
+    Ok(Object {
+        kind: ObjectKind::Float,
+        data: ObjectData::Float(if negative { -whole } else { whole }, prec),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, Literal};
+    use crate::{
+        object::{Object, ObjectData},
+        program::Program,
+    };
+
+    fn data(text: &str) -> Result<Option<ObjectData>, String> {
+        parse(&mut Program::new(), text).map(|lit| {
+            lit.map(|lit| match lit {
+                Literal::Value(obj) => obj.data,
+                Literal::List(_) => panic!("not a list"),
+            })
+        })
+    }
+
+    #[test]
+    fn integers() {
+        assert_eq!(data("-5"), Ok(Some(ObjectData::Integer(-5))));
+        assert_eq!(data("0xff"), Ok(Some(ObjectData::Integer(255))));
+        assert_eq!(data("-0b1010"), Ok(Some(ObjectData::Integer(-10))));
+        assert_eq!(data("1_000_000"), Ok(Some(ObjectData::Integer(1_000_000))));
+        assert!(data("1__0").is_ok());
+        assert!(data("_1").is_ok(), "a name");
+        assert!(data("1_").is_err());
+        assert!(data("0x").is_err());
+        assert!(data("0b12").is_err());
+        assert!(data("99999999999999999999").is_err());
+    }
+
+    #[test]
+    fn floats() {
+        assert_eq!(data("-1.5"), Ok(Some(ObjectData::Float(-1, 5))));
+        assert_eq!(data("1e3"), Ok(Some(ObjectData::Float(1000, 0))));
+        assert_eq!(data("2.5e-1"), Ok(Some(ObjectData::Float(0, 25))));
+        assert_eq!(data("1_0.2_5E+1"), Ok(Some(ObjectData::Float(102, 5))));
+        assert!(data("-0.5").is_err());
+        assert!(data("5e-2").is_err());
+        assert!(data("0.05").is_err());
+        assert!(data("1.05").is_err());
+        assert_eq!(data("5.0e-1"), Ok(Some(ObjectData::Float(0, 5))));
+        assert!(data("1.2.3").is_err());
+        assert!(data("1e").is_err());
+        assert!(data("1e99999999999").is_err());
+    }
+
+    #[test]
+    fn names_are_not_literals() {
+        assert_eq!(data("fizz"), Ok(None));
+        assert_eq!(data("true"), Ok(Some(ObjectData::Bool(true))));
+    }
+
+    #[test]
+    fn lists() {
+        let mut program = Program::new();
+        let int = |n: isize| Literal::Value(Object::from(n));
+        let parsed = parse(&mut program, "[1, \"a, ]\", [-2, []], Nil]")
+            .unwrap()
+            .unwrap();
+        let Literal::List(items) = parsed else {
+            panic!("not a list")
+        };
+        assert_eq!(items.len(), 4);
+        assert_eq!(items[0], int(1));
+        assert!(matches!(items[1], Literal::Value(obj) if obj.to_string() == "a, ]"));
+        assert_eq!(
+            items[2],
+            Literal::List(vec![int(-2), Literal::List(vec![])])
+        );
+
+        for bad in ["[1, 2", "[1,, 2]", "[1] 2", "[fizz]", "[\"open]"] {
+            assert!(parse(&mut program, bad).is_err(), "{bad}");
+        }
+    }
+}
@@ -1,51 +1,58 @@
+extern crate jed;
 extern crate lexopt;
-mod arena;
-mod binops;
-mod builtin;
-mod error;
-mod frame;
-mod indexmap;
-mod map;
-mod modules;
-mod object;
-mod operation;
-mod program;
-mod span;
-mod stack;
-mod utils;
-mod vm;
+mod debugger;
+mod fmt;
+mod golden;
+mod profiler;
+mod repl;
 use std::{
-    fs::File,
+    fs::{self, File},
     io::{self, Read, Seek, SeekFrom},
     path::Path,
 };
 
-use program::Program;
-use vm::VM;
+use jed::{bytes_to_string, ParseError, Program, MAGIC_NUMBER, VM};
 
 const HELP: &str = "jed [COMMAND] [OPTIONS]";
-const MAGIC_NUMBER: &[u8] = "jed".as_bytes();
 
 // jed commands:
 //  - compile (string -> bytecode)
-//  - run (string | bytecode)
+//  - run (string | bytecode, anything after `--` is passed to main)
 //  - validate (string | bytecode)
+//  - disasm (bytecode -> string)
+//  - repl (string, optionally preloading a file)
+//  - test (directory of string | bytecode, compared against expected output)
+//  - fmt (string, rewritten in place)
+//  - debug (string | bytecode, stepped through interactively)
 //
 // flags:
 //  - --output/-o (path to cache dir)
-//  - --debug
+//  - --debug (trace every instruction to stderr)
+//  - --trace-func NAME (only trace inside NAME, repeatable)
+//  - --check (fmt only reports unformatted files)
+//  - --profile (run prints a profile and writes folded stacks to the cache dir)
 
 struct Args {
     command: Command,
-    file: String,
+    file: Option<String>,
     output: String,
     debug: bool,
+    check: bool,
+    profile: bool,
+    trace_funcs: Vec<String>,
+    // everything after `--`, handed to `main`
+    args: Vec<String>,
 }
 
 enum Command {
     Compile,
     Run,
     Validate,
+    Disasm,
+    Repl,
+    Test,
+    Fmt,
+    Debug,
 }
 
 fn parse_args() -> Result<Args, lexopt::Error> {
@@ -55,9 +62,25 @@ fn parse_args() -> Result<Args, lexopt::Error> {
     let mut file: Option<String> = None;
     let mut output: Option<String> = None;
     let mut debug = false;
+    let mut check = false;
+    let mut profile = false;
+    let mut trace_funcs: Vec<String> = vec![];
+    let mut args: Vec<String> = vec![];
 
     let mut parser = lexopt::Parser::from_env();
-    while let Some(arg) = parser.next()? {
+    loop {
+        // everything after `--` goes to `main` as it is
+        if let Some(mut raw) = parser.try_raw_args() {
+            if raw.next_if(|arg| arg == "--").is_some() {
+                for arg in raw {
+                    args.push(arg.string()?);
+                }
+                break;
+            }
+        }
+        let Some(arg) = parser.next()? else {
+            break;
+        };
         match arg {
             Value(cmd) if command.is_none() => {
                 let cmd = cmd.string()?;
@@ -65,12 +88,23 @@ fn parse_args() -> Result<Args, lexopt::Error> {
                     "compile" => Some(Command::Compile),
                     "run" => Some(Command::Run),
                     "validate" => Some(Command::Validate),
+                    "disasm" => Some(Command::Disasm),
+                    "repl" => Some(Command::Repl),
+                    "test" => Some(Command::Test),
+                    "fmt" => Some(Command::Fmt),
+                    "debug" => Some(Command::Debug),
                     _ => Err(format!("unknown command '{}'", cmd))?,
                 }
             }
             Value(fl) if file.is_none() => file = Some(fl.string()?),
             Short('o') | Long("output") => output = Some(parser.value()?.parse()?),
             Long("debug") => debug = true,
+            Long("check") => check = true,
+            Long("profile") => profile = true,
+            Long("trace-func") => {
+                trace_funcs.push(parser.value()?.string()?);
+                debug = true;
+            }
             Short('h') | Long("help") => {
                 println!("{}", HELP);
                 std::process::exit(0);
@@ -78,11 +112,18 @@ fn parse_args() -> Result<Args, lexopt::Error> {
             _ => return Err(arg.unexpected()),
         }
     }
+    if !args.is_empty() && !matches!(command, Some(Command::Run | Command::Debug)) {
+        Err("only run and debug pass arguments to main")?;
+    }
     Ok(Args {
         command: command.unwrap_or_else(|| panic!("missing command")),
-        file: file.unwrap_or_else(|| panic!()),
+        file,
         output: output.unwrap_or(".jedcache/".to_owned()),
         debug,
+        check,
+        profile,
+        trace_funcs,
+        args,
     })
 }
 
@@ -91,21 +132,26 @@ fn main() -> io::Result<()> {
         Ok(opts) => opts,
         Err(e) => panic!("{}", e),
     };
-    let filepath = Path::new(&opts.file);
+    if let Command::Repl = opts.command {
+        let preload = match &opts.file {
+            Some(file) => Some(fs::read_to_string(file)?),
+            None => None,
+        };
+        return repl::run(preload, opts.debug);
+    }
+    let file = opts.file.unwrap_or_else(|| panic!("missing file"));
+    let filepath = Path::new(&file);
     let output = Path::new(&opts.output);
-    // output doesnt work
-    // but yk
 
     match opts.command {
         Command::Compile => {
             let mut file = File::open(filepath)?;
             let mut string = String::new();
             file.read_to_string(&mut string)?;
-            let program = Program::from_string(string);
+            let program = parsed(filepath, Program::from_string(&string));
 
-            let mut output_filepath = output.to_path_buf();
-            let file_name = filepath.file_stem().unwrap();
-            output_filepath.set_file_name(file_name);
+            fs::create_dir_all(output)?;
+            let mut output_filepath = output.join(filepath.file_stem().unwrap());
             output_filepath.set_extension("jbc");
 
             let mut output_file = File::create(&output_filepath)?;
@@ -113,35 +159,172 @@ fn main() -> io::Result<()> {
             println!("wrote to {}", output_filepath.to_str().unwrap());
         }
         Command::Run => {
-            let mut file = File::open(filepath)?;
-            let mut magic_number_buffer = [0 as u8; 3];
-            let n = file.read(&mut magic_number_buffer)?;
-            if n == 3 && magic_number_buffer == MAGIC_NUMBER {
-                file.seek(SeekFrom::Start(0))?;
-                let mut vm = VM::from_file(&mut file, opts.debug)?;
-                vm.run();
-            } else {
-                file.seek(SeekFrom::Start(0))?;
-                let mut string = String::new();
-                file.read_to_string(&mut string)?;
-                let mut vm = VM::from_string(string, opts.debug);
-                vm.run();
+            let program = parsed(filepath, load_program(filepath, output)?);
+            let mut vm = VM::new(program, opts.debug);
+            vm.trace_funcs = opts.trace_funcs;
+            if opts.profile {
+                fs::create_dir_all(output)?;
+                let mut folded_path = output.join(filepath.file_stem().unwrap());
+                folded_path.set_extension("folded");
+                vm.hooks.push(Box::new(profiler::Profiler::new(folded_path)));
             }
+            run_vm(&mut vm, &opts.args);
+        }
+        Command::Debug => {
+            let program = parsed(filepath, load_program(filepath, output)?);
+            let mut vm = VM::new(program, opts.debug);
+            vm.hooks.push(Box::new(debugger::Debugger::new()));
+            run_vm(&mut vm, &opts.args);
         }
         Command::Validate => {
             let mut file = File::open(filepath)?;
-            let mut magic_number_buffer = [0 as u8; 3];
+            let mut magic_number_buffer = [0u8; 3];
             let n = file.read(&mut magic_number_buffer)?;
             if n == 3 && magic_number_buffer == MAGIC_NUMBER {
                 file.seek(SeekFrom::Start(0))?;
-                let _program = Program::from_file(&mut file)?;
+                parsed(filepath, Program::from_file(&mut file));
             } else {
                 file.seek(SeekFrom::Start(0))?;
                 let mut string = String::new();
                 file.read_to_string(&mut string)?;
-                let _program = Program::from_string(string);
+                let errors = Program::new().parse_string(&string);
+                for ParseError(kind, line) in &errors {
+                    eprintln!("{}:{line}: {kind}", filepath.display());
+                }
+                if !errors.is_empty() {
+                    eprintln!(
+                        "{} problem{} found",
+                        errors.len(),
+                        if errors.len() > 1 { "s" } else { "" }
+                    );
+                    std::process::exit(1);
+                }
+            }
+        }
+        Command::Disasm => {
+            let mut file = File::open(filepath)?;
+            let program = parsed(filepath, Program::from_file(&mut file));
+            print!("{}", program.disassemble());
+        }
+        Command::Repl => unreachable!(),
+        Command::Fmt => {
+            let text = fs::read_to_string(filepath)?;
+            // make sure it parses before touching it
+            parsed(filepath, Program::from_string(&text));
+            let formatted = fmt::format(&text);
+            if formatted == text {
+                return Ok(());
+            }
+            if opts.check {
+                println!("{} is not formatted", filepath.display());
+                std::process::exit(1);
+            }
+            fs::write(filepath, formatted)?;
+            println!("formatted {}", filepath.display());
+        }
+        Command::Test => {
+            if !golden::run(filepath, output)? {
+                std::process::exit(1);
             }
         }
     }
     Ok(())
 }
+
+/// Runs the program, a runtime error goes to stderr and picks the exit code.
+fn run_vm(vm: &mut VM, args: &[String]) {
+    if let Err(e) = vm.run_with_args(args) {
+        eprintln!("runtime failure:\n{e}");
+        std::process::exit(e.0.exit_code());
+    }
+}
+
+/// Unwraps a parsed program, a parse error goes to stderr and ends the process.
+fn parsed(filepath: &Path, program: Result<Program, ParseError>) -> Program {
+    match program {
+        Ok(program) => program,
+        Err(e) => {
+            eprintln!("{}: {e}", filepath.display());
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Loads either Bytecode or string Jed Bytecode, the latter through the cache.
+fn load_program(filepath: &Path, cache_dir: &Path) -> io::Result<Result<Program, ParseError>> {
+    let mut file = File::open(filepath)?;
+    let mut magic_number_buffer = [0u8; 3];
+    let n = file.read(&mut magic_number_buffer)?;
+    file.seek(SeekFrom::Start(0))?;
+    if n == 3 && magic_number_buffer == MAGIC_NUMBER {
+        Ok(Program::from_file(&mut file))
+    } else {
+        let mut string = String::new();
+        file.read_to_string(&mut string)?;
+        cached_program(filepath, cache_dir, string)
+    }
+}
+
+/// Loads the compiled version of `text` from the cache directory, compiling and
+/// saving it first if the source has changed since the last run.
+///
+/// Cached files are named `<file stem>-<hash of the path>-<hash of the source>.jbc`,
+/// with the file's canonical path, and older entries for the same path are
+/// removed when a new one is written. The source hash also covers the `jed`
+/// version, since the Bytecode format changes between them.
+/// A cached file that doesn't load is thrown away and recompiled.
+fn cached_program(
+    filepath: &Path,
+    cache_dir: &Path,
+    text: String,
+) -> io::Result<Result<Program, ParseError>> {
+    let stem = bytes_to_string(filepath.file_stem().unwrap().as_encoded_bytes());
+    // files with the same name in different directories get their own entries
+    let source = fs::canonicalize(filepath)?;
+    let prefix = format!("{stem}-{:016x}-", hash_bytes(source.as_os_str().as_encoded_bytes()));
+    let keyed = format!("{}\n{text}", env!("CARGO_PKG_VERSION"));
+    let cache_name = format!("{prefix}{:016x}.jbc", hash_bytes(keyed.as_bytes()));
+    let cache_path = cache_dir.join(&cache_name);
+
+    let name = filepath.display().to_string();
+    if let Ok(mut cached) = File::open(&cache_path) {
+        match Program::from_file(&mut cached) {
+            Ok(mut program) => {
+                program.attach_source(&name, &text);
+                return Ok(Ok(program));
+            }
+            Err(_) => fs::remove_file(&cache_path)?,
+        }
+    }
+
+    let program = match Program::from_source(&name, &text) {
+        Ok(program) => program,
+        Err(e) => return Ok(Err(e)),
+    };
+    fs::create_dir_all(cache_dir)?;
+    for entry in fs::read_dir(cache_dir)? {
+        let name = entry?.file_name();
+        let name = name.to_string_lossy();
+        let is_stale = name
+            .strip_prefix(&prefix)
+            .and_then(|rest| rest.strip_suffix(".jbc"))
+            .is_some_and(|hash| hash.len() == 16 && hash.chars().all(|c| c.is_ascii_hexdigit()));
+        if is_stale {
+            fs::remove_file(cache_dir.join(name.as_ref()))?;
+        }
+    }
+    let mut cache_file = File::create(&cache_path)?;
+    program.to_file(&mut cache_file)?;
+    Ok(Ok(program))
+}
+
+/// 64-bit FNV-1a, used for naming cached bytecode. Unlike `DefaultHasher` the
+/// result is stable between builds, so the cache survives a toolchain update.
+fn hash_bytes(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in bytes {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
@@ -1,15 +1,16 @@
 use std::{convert::TryInto, fmt::Display};
 
 use crate::{
-    binops::BinOpKind,
+    binops::{self, BinOpKind},
     builtin::BuiltIn,
     error::{ProgramError, ProgramErrorKind},
     frame::{Frame, FrameKind},
+    literal::Literal,
     modules::{self, MODULES},
     object::{Object, ObjectData, ObjectKind},
     stack::Stack,
     utils::{self, bytes_to_string},
-    vm::VM,
+    vm::{Unwind, VM},
 };
 
 #[derive(Copy, Clone, Debug)]
@@ -49,6 +50,26 @@ pub enum Operation {
     DoIf,
     Debug,
     Import(&'static [u8]),
+    Label(&'static [u8]),
+    Jump(&'static [u8]),
+    JumpIf(&'static [u8]),
+    JumpUnless(&'static [u8]),
+    Else,
+    DoWhile,
+    Check,
+    Break,
+    Continue,
+    BreakIf,
+    ContinueIf,
+    Return,
+    Dup,
+    Swap,
+    Over,
+    Rot,
+    Pick(Option<usize>),
+    Drop(Option<usize>),
+    Neg,
+    Not,
     Empty,
 }
 
@@ -58,6 +79,8 @@ impl From<(u8, Option<usize>)> for Operation {
             17 => Operation::CreateList(value.1),
             19 => Operation::ListGet(value.1),
             20 => Operation::ListSet(value.1),
+            51 => Operation::Pick(value.1),
+            52 => Operation::Drop(value.1),
             _ => panic!(),
         }
     }
@@ -75,6 +98,10 @@ impl From<(u8, &'static [u8])> for Operation {
             16 => Operation::DoForIn(value.1),
             22 => Operation::ReturnIfConst(value.1),
             31 => Operation::ReturnIfConst(value.1),
+            35 => Operation::Label(value.1),
+            36 => Operation::Jump(value.1),
+            37 => Operation::JumpIf(value.1),
+            38 => Operation::JumpUnless(value.1),
             _ => panic!(),
         }
     }
@@ -99,7 +126,23 @@ impl From<u8> for Operation {
             28 => Operation::IterPrev,
             29 => Operation::IterSkip,
             30 => Operation::IterCurrent,
+            31 => Operation::Iterate,
+            32 => Operation::DoIf,
             33 => Operation::Debug,
+            39 => Operation::Else,
+            40 => Operation::DoWhile,
+            41 => Operation::Check,
+            42 => Operation::Break,
+            43 => Operation::Continue,
+            44 => Operation::BreakIf,
+            45 => Operation::ContinueIf,
+            46 => Operation::Return,
+            47 => Operation::Dup,
+            48 => Operation::Swap,
+            49 => Operation::Over,
+            50 => Operation::Rot,
+            53 => Operation::Neg,
+            54 => Operation::Not,
             _ => panic!(),
         }
     }
@@ -142,6 +185,26 @@ impl From<Operation> for u8 {
             Operation::DoIf => 32,
             Operation::Debug => 33,
             Operation::Import(_) => 34,
+            Operation::Label(_) => 35,
+            Operation::Jump(_) => 36,
+            Operation::JumpIf(_) => 37,
+            Operation::JumpUnless(_) => 38,
+            Operation::Else => 39,
+            Operation::DoWhile => 40,
+            Operation::Check => 41,
+            Operation::Break => 42,
+            Operation::Continue => 43,
+            Operation::BreakIf => 44,
+            Operation::ContinueIf => 45,
+            Operation::Return => 46,
+            Operation::Dup => 47,
+            Operation::Swap => 48,
+            Operation::Over => 49,
+            Operation::Rot => 50,
+            Operation::Pick(_) => 51,
+            Operation::Drop(_) => 52,
+            Operation::Neg => 53,
+            Operation::Not => 54,
             Operation::Empty => todo!(),
         }
     }
@@ -192,12 +255,93 @@ impl Display for Operation {
             Operation::DoIf => write!(f, "do_if"),
             Operation::Debug => write!(f, "debug"),
             Operation::Import(bytes) => write!(f, "import {}", bytes_to_string(bytes)),
+            Operation::Label(bytes) => write!(f, "label {}", bytes_to_string(bytes)),
+            Operation::Jump(bytes) => write!(f, "jump {}", bytes_to_string(bytes)),
+            Operation::JumpIf(bytes) => write!(f, "jump_if {}", bytes_to_string(bytes)),
+            Operation::JumpUnless(bytes) => write!(f, "jump_unless {}", bytes_to_string(bytes)),
+            Operation::Else => write!(f, "else"),
+            Operation::DoWhile => write!(f, "do_while"),
+            Operation::Check => write!(f, "check"),
+            Operation::Break => write!(f, "break"),
+            Operation::Continue => write!(f, "continue"),
+            Operation::BreakIf => write!(f, "break_if"),
+            Operation::ContinueIf => write!(f, "continue_if"),
+            Operation::Return => write!(f, "return"),
+            Operation::Dup => write!(f, "dup"),
+            Operation::Swap => write!(f, "swap"),
+            Operation::Over => write!(f, "over"),
+            Operation::Rot => write!(f, "rot"),
+            Operation::Pick(n) => write!(f, "pick {}", utils::unwrap_as_string_or(*n, "")),
+            Operation::Drop(n) => write!(f, "drop {}", utils::unwrap_as_string_or(*n, "")),
+            Operation::Neg => write!(f, "neg"),
+            Operation::Not => write!(f, "not"),
             Operation::Empty => write!(f, ""),
         }
     }
 }
 
 impl Operation {
+    /// The text form of the instruction without its argument.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Operation::BinOp(_) => "bin_op",
+            Operation::Call(_) => "call",
+            Operation::CallBuiltIn(_) => "call_builtin",
+            Operation::PushLit(_) => "push_lit",
+            Operation::PushName(_) => "push_name",
+            Operation::PushTemp => "push_temp",
+            Operation::Pop => "pop",
+            Operation::ReturnIf(_) => "return_if",
+            Operation::StoreConst(_) => "store_const",
+            Operation::StoreName(_) => "store_name",
+            Operation::StoreTemp => "store_temp",
+            Operation::Func(_, _) => "func",
+            Operation::Done => "done",
+            Operation::Exit => "exit",
+            Operation::DoFor => "do_for",
+            Operation::DoForIn(_) => "do_for_in",
+            Operation::CreateList(_) => "create_list",
+            Operation::ListPush => "list_push",
+            Operation::ListGet(_) => "list_get",
+            Operation::ListSet(_) => "list_set",
+            Operation::PushRange => "push_range",
+            Operation::ReturnIfConst(_) => "return_if_const",
+            Operation::GetPtr => "get_ptr",
+            Operation::ReadPtr => "read_ptr",
+            Operation::SetPtr => "set_ptr",
+            Operation::GetIter => "get_iter",
+            Operation::IterNext => "iter_next",
+            Operation::IterPrev => "iter_prev",
+            Operation::IterSkip => "iter_skip",
+            Operation::IterCurrent => "iter_current",
+            Operation::Iterate => "iterate",
+            Operation::DoIf => "do_if",
+            Operation::Debug => "debug",
+            Operation::Import(_) => "import",
+            Operation::Label(_) => "label",
+            Operation::Jump(_) => "jump",
+            Operation::JumpIf(_) => "jump_if",
+            Operation::JumpUnless(_) => "jump_unless",
+            Operation::Else => "else",
+            Operation::DoWhile => "do_while",
+            Operation::Check => "check",
+            Operation::Break => "break",
+            Operation::Continue => "continue",
+            Operation::BreakIf => "break_if",
+            Operation::ContinueIf => "continue_if",
+            Operation::Return => "return",
+            Operation::Dup => "dup",
+            Operation::Swap => "swap",
+            Operation::Over => "over",
+            Operation::Rot => "rot",
+            Operation::Pick(_) => "pick",
+            Operation::Drop(_) => "drop",
+            Operation::Neg => "neg",
+            Operation::Not => "not",
+            Operation::Empty => "",
+        }
+    }
+
     pub fn exists(s: &str) -> bool {
         match s {
             "bin_op" => true,
@@ -234,10 +378,46 @@ impl Operation {
             "do_if" => true,
             "debug" => true,
             "import" => true,
+            "label" => true,
+            "jump" => true,
+            "jump_if" => true,
+            "jump_unless" => true,
+            "else" => true,
+            "do_while" => true,
+            "check" => true,
+            "break" => true,
+            "continue" => true,
+            "break_if" => true,
+            "continue_if" => true,
+            "return" => true,
+            "dup" => true,
+            "swap" => true,
+            "over" => true,
+            "rot" => true,
+            "pick" => true,
+            "drop" => true,
+            "neg" => true,
+            "not" => true,
             _ => false,
         }
     }
 
+    /// Whether the operation named `op` starts a block that needs closing.
+    pub fn opens_block(op: &str) -> bool {
+        matches!(op, "func" | "do_for" | "do_for_in" | "iterate" | "do_if" | "do_while")
+    }
+
+    /// Whether the operation named `op` starts another branch of the innermost
+    /// open block, so it sits at the block's own indentation.
+    pub fn branches_block(op: &str) -> bool {
+        matches!(op, "else" | "check")
+    }
+
+    /// Whether the operation named `op` closes the innermost open block.
+    pub fn closes_block(op: &str) -> bool {
+        matches!(op, "done" | "exit")
+    }
+
     pub fn get_opcode(op: &str) -> usize {
         match op {
             "bin_op" => 1,
@@ -274,11 +454,46 @@ impl Operation {
             "do_if" => 32,
             "debug" => 33,
             "import" => 34,
+            "label" => 35,
+            "jump" => 36,
+            "jump_if" => 37,
+            "jump_unless" => 38,
+            "else" => 39,
+            "do_while" => 40,
+            "check" => 41,
+            "break" => 42,
+            "continue" => 43,
+            "break_if" => 44,
+            "continue_if" => 45,
+            "return" => 46,
+            "dup" => 47,
+            "swap" => 48,
+            "over" => 49,
+            "rot" => 50,
+            "pick" => 51,
+            "drop" => 52,
+            "neg" => 53,
+            "not" => 54,
             _ => 0,
         }
     }
 
     pub fn call(&self, vm: &mut VM) -> Result<(), ProgramError> {
+        // these run their block inside this call, so they're kept apart from
+        // the rest, every nested block only costs the stack space used here
+        match self {
+            Operation::DoFor => do_for(vm),
+            Operation::DoForIn(obj_name) => do_for_in(vm, obj_name),
+            Operation::Iterate => iterate(vm),
+            Operation::DoIf => do_if(vm),
+            Operation::DoWhile => do_while(vm),
+            _ => self.call_flat(vm),
+        }
+    }
+
+    /// Runs the operations that don't run a block.
+    #[inline(never)]
+    fn call_flat(&self, vm: &mut VM) -> Result<(), ProgramError> {
         match self {
             Operation::BinOp(bin_op_kind) => vm.handle_bin_op(*bin_op_kind),
             Operation::Call(func) => {
@@ -305,7 +520,7 @@ impl Operation {
                             Ok(ts) => Ok(ts),
                             Err(_) => vm.error(ProgramErrorKind::StackError(arity)),
                         }?;
-                        let value = vm.register_single(*value);
+                        let value = vm.register_single(value);
                         vm.obj_stack.push(value);
                         Ok(())
                     }
@@ -331,61 +546,25 @@ impl Operation {
                 if let Some(lit) = get_const {
                     vm.obj_stack.push(lit);
                 } else {
-                    let string = unsafe { String::from_utf8_unchecked(literal.to_vec()) };
-                    let obj = {
-                        if string.starts_with('[') && string.ends_with(']') {
-                            // let literals = &string[1..string.len() - 1];
-                            todo!("pushing many at a time")
-                        } else if string.starts_with('"') && string.ends_with('"') {
-                            let s = &string[1..string.len() - 1];
-                            let sb = vm.program.register(s.to_owned());
-                            vm.register_single(Object {
-                                kind: ObjectKind::String,
-                                data: ObjectData::String(sb),
-                            })
-                        } else if string == "true" {
-                            vm.register_single(Object {
-                                kind: ObjectKind::Bool,
-                                data: ObjectData::Bool(true),
-                            })
-                        } else if string == "false" {
-                            vm.register_single(Object {
-                                kind: ObjectKind::Bool,
-                                data: ObjectData::Bool(false),
-                            })
-                        } else if string == "Nil" {
-                            vm.register_single(Object::nil())
-                        } else if string.chars().all(|c| c.is_numeric()) {
-                            let num: isize = match utils::string_to_t(string) {
-                                Ok(v) => v,
-                                Err(e) => return vm.error(e),
-                            };
-                            vm.register_single(Object {
-                                kind: ObjectKind::Integer,
-                                data: ObjectData::Integer(num),
-                            })
-                        } else if utils::string_is_float_like(string.clone()) {
-                            let (wholestr, precstr) = string.split_at(string.find('.').unwrap());
-                            let whole: i32 = match utils::string_to_t(wholestr.to_owned()) {
-                                Ok(v) => v,
-                                Err(e) => return vm.error(e),
-                            };
-                            let prec: u32 = match utils::string_to_t(precstr[1..].to_owned()) {
-                                Ok(v) => v,
-                                Err(e) => return vm.error(e),
-                            };
-                            vm.register_single(Object {
-                                kind: ObjectKind::Float,
-                                data: ObjectData::Float(whole, prec),
-                            })
-                        } else {
+                    // parsed when the program was loaded, see `Program::add_literal`
+                    match vm.program.literals.get(literal) {
+                        Some(Literal::Value(obj)) => {
+                            let obj = vm.register_single(*obj);
+                            vm.obj_stack.push(obj);
+                            vm.store_const(literal, *obj);
+                        }
+                        // a new list every time, the last one may have been changed
+                        Some(list) => {
+                            let list = list.clone();
+                            let obj = vm.alloc_literal(&list);
+                            vm.obj_stack.push(obj);
+                        }
+                        None => {
                             return vm.error(ProgramErrorKind::ParsingError(
                                 utils::bytes_to_string(literal),
-                            ));
+                            ))
                         }
-                    };
-                    vm.obj_stack.push(obj);
-                    vm.store_const(literal, *obj);
+                    }
                 }
                 Ok(())
             }
@@ -430,7 +609,9 @@ impl Operation {
                         Err(_) => vm.error(ProgramErrorKind::StackError(1)),
                     }
                 }?;
-                assert_eq!(b.kind, ObjectKind::Bool, "Object is not a boolean");
+                if b.kind != ObjectKind::Bool {
+                    return vm.error(ProgramErrorKind::TypeError(ObjectKind::Bool, b.kind));
+                }
                 if let ObjectData::Bool(bol) = b.data {
                     if bol {
                         let frame = {
@@ -496,25 +677,24 @@ impl Operation {
                     }
                 }?;
                 match frame.kind {
-                    FrameKind::Call => {
-                        let return_value = *vm.obj_stack.last_option().unwrap_or(&&Object {
-                            kind: ObjectKind::Nil,
-                            data: ObjectData::Nil,
-                        });
-                        vm.program.set_memo(frame.memo_key, *return_value);
-                        vm.counter = frame.return_address;
-                    }
-                    FrameKind::IterateLoop | FrameKind::DoForLoop | FrameKind::DoForInLoop => {
+                    FrameKind::Call => vm.finish_call(&frame),
+                    FrameKind::IterateLoop
+                    | FrameKind::DoForLoop
+                    | FrameKind::DoForInLoop
+                    | FrameKind::DoWhileLoop => {
                         vm.call_stack.push(frame);
                     }
                     FrameKind::DoIfBlock => {
                         return Ok(());
                     }
-                    FrameKind::Main => vm.exit(Some(0)),
+                    FrameKind::Main => vm.exit(),
                 }
                 Ok(())
             }
-            Operation::Exit => Ok(vm.exit(Some(0))),
+            Operation::Exit => {
+                vm.exit();
+                Ok(())
+            }
             Operation::CallBuiltIn(built_in) => {
                 let obj = {
                     match { vm.obj_stack.pop() } {
@@ -522,85 +702,12 @@ impl Operation {
                         Err(e) => vm.error(e),
                     }
                 }?;
-                if let Some(val) = built_in.call(*obj) {
+                if let Some(val) = built_in.call(*obj, &mut vm.output) {
                     let val = vm.register_single(val);
                     vm.obj_stack.push(val);
                 };
                 Ok(())
             }
-            Operation::DoFor => {
-                let object = {
-                    match { vm.obj_stack.pop() } {
-                        Ok(t) => Ok(t),
-                        Err(e) => vm.error(e),
-                    }
-                }?;
-                if let ObjectData::Integer(times) = object.data {
-                    let pc = vm.counter.clone();
-                    let last_frame = match vm.call_stack.last() {
-                        Ok(it) => it,
-                        Err(err) => vm.error(err)?,
-                    };
-                    let mut new_frame = Frame::new(pc, FrameKind::DoForLoop);
-                    new_frame.copy_locals(last_frame);
-                    vm.call_stack.push(new_frame.clone());
-                    for _ in 0..times {
-                        vm.counter = pc;
-                        vm.run_block(FrameKind::DoForLoop);
-                    }
-                    let _ = vm.call_stack.pop();
-                    let done_address = vm.program.get_done(&(pc - 1));
-                    match done_address {
-                        Ok(addy) => vm.goto(*addy + 1),
-                        Err(e) => vm.error(e)?,
-                    }
-                }
-                Ok(())
-            }
-            Operation::DoForIn(obj_name) => {
-                let current_frame = {
-                    match vm.call_stack.last() {
-                        Ok(ts) => Ok(ts),
-                        Err(_) => vm.error(ProgramErrorKind::StackError(1)),
-                    }
-                }?;
-                let maybe_obj_ptr = current_frame.get_local(obj_name);
-                let obj_ptr = maybe_obj_ptr.unwrap_or_else(|| {
-                    panic!("No such name '{}'", utils::bytes_to_string(obj_name))
-                });
-                let pc = vm.counter.clone();
-                let mut new_frame = Frame::new(pc, FrameKind::DoForInLoop);
-                new_frame.copy_locals(current_frame);
-                vm.call_stack.push(new_frame);
-                match obj_ptr.as_tuple() {
-                    (ObjectKind::List, ObjectData::List(_start, len)) => unsafe {
-                        for _ in 0..*len {
-                            vm.counter = pc;
-                            vm.run_block(FrameKind::DoForInLoop);
-                        }
-                    },
-                    (ObjectKind::Iterator, ObjectData::Iterator(list_ptr, _next)) => unsafe {
-                        let list = *list_ptr;
-                        if let ObjectData::List(_start, len) = list {
-                            for _ in 0..*len {
-                                vm.counter = pc;
-                                vm.run_block(FrameKind::DoForInLoop);
-                            }
-                        }
-                    },
-
-                    (kind, _data) => {
-                        return vm.error(ProgramErrorKind::TypeError(ObjectKind::List, kind))
-                    }
-                }
-                let _ = vm.call_stack.pop();
-                let done_address = vm.program.get_done(&(pc - 1));
-                match done_address {
-                    Ok(addy) => vm.goto(*addy + 1),
-                    Err(e) => vm.error(e)?,
-                }
-                Ok(())
-            }
             Operation::CreateList(maybe_num) => {
                 let num = match maybe_num {
                     Some(v) => *v,
@@ -611,29 +718,8 @@ impl Operation {
                     Ok(objs) => objs.iter().map(|o| **o).collect(),
                     Err(e) => return vm.error(e),
                 };
-                if objects.len() > 0 {
-                    let objects: &'static [Object] = vm.register_many(objects.as_slice());
-                    let len = Box::new(objects.len());
-                    let obj_ptr = objects.as_ptr();
-                    let obj_ptr: Box<usize> = Box::new(obj_ptr.addr());
-                    let obj = Object {
-                        kind: ObjectKind::List,
-                        data: ObjectData::List(Box::into_raw(obj_ptr), Box::into_raw(len)),
-                    };
-                    let obj: &'static Object = vm.register_single(obj);
-
-                    vm.obj_stack.push(obj);
-                } else {
-                    let len = Box::new(0);
-                    let random_addr = Box::new(vm.memory.start().addr());
-                    let obj = Object {
-                        kind: ObjectKind::List,
-                        data: ObjectData::List(Box::into_raw(random_addr), Box::into_raw(len)),
-                    };
-                    let obj: &'static Object = vm.register_single(obj);
-                    vm.obj_stack.push(obj);
-                }
-
+                let obj = vm.alloc_list(&objects);
+                vm.obj_stack.push(obj);
                 Ok(())
             }
             Operation::ListPush => unsafe {
@@ -803,7 +889,9 @@ impl Operation {
                         Err(_) => vm.error(ProgramErrorKind::StackError(1)),
                     }
                 }?;
-                assert_eq!(b.kind, ObjectKind::Bool, "Object is not a boolean");
+                if b.kind != ObjectKind::Bool {
+                    return vm.error(ProgramErrorKind::TypeError(ObjectKind::Bool, b.kind));
+                }
                 if let ObjectData::Bool(bol) = b.data {
                     if bol {
                         let frame = {
@@ -988,81 +1076,63 @@ impl Operation {
                 }
                 Ok(())
             },
-            Operation::Iterate => unsafe {
-                let Object { kind, data } = {
-                    match { vm.obj_stack.pop_mut() } {
-                        Ok(&mut t) => Ok(t),
-                        Err(_) => vm.error(ProgramErrorKind::StackError(1)),
-                    }
-                }?;
-                if let ObjectData::Iterator(list_ptr, next) = data {
-                    let list = **list_ptr;
-                    if let ObjectData::List(start, len) = list {
-                        if *len != 0 && **next < *len {
-                            let start = *start as *const Object;
-                            let pc = vm.counter.clone();
-                            let last_frame = match vm.call_stack.last() {
-                                Ok(it) => it,
-                                Err(err) => vm.error(err)?,
-                            };
-                            let mut new_frame = Frame::new(pc, FrameKind::IterateLoop);
-                            new_frame.copy_locals(last_frame);
-                            vm.call_stack.push(new_frame);
-
-                            for n in (**next)..*len {
-                                **next = n + 1;
-                                vm.counter = pc;
-                                // println!("iterate: {}, pc: {pc}", &*start.add(n));
-                                vm.obj_stack.push(&*start.add(n));
-
-                                vm.run_block(FrameKind::IterateLoop);
-                            }
-                            let _ = vm.call_stack.pop();
-                            let done_address = vm.program.get_done(&(pc - 1));
-                            match done_address {
-                                Ok(addy) => vm.goto(*addy + 1),
-                                Err(e) => vm.error(e)?,
-                            }
-                        } else {
-                            let done_address = vm.program.get_done(&(vm.counter - 1));
-                            match done_address {
-                                Ok(addy) => vm.goto(*addy),
-                                Err(e) => vm.error(e)?,
-                            }
-                        }
-                    }
+            // `do_while` stops in front of it and looks at the condition itself
+            Operation::Check => Ok(()),
+            Operation::Return => vm.return_from_function(),
+            Operation::Neg => vm.handle_un_op(binops::neg),
+            Operation::Not => vm.handle_un_op(binops::not),
+            Operation::Dup => vm.pick(0),
+            Operation::Over => vm.pick(1),
+            Operation::Swap => vm.rotate(2),
+            Operation::Rot => vm.rotate(3),
+            Operation::Pick(maybe_n) => {
+                let n = match maybe_n {
+                    Some(n) => *n,
+                    None => vm.pop_count()?,
+                };
+                vm.pick(n)
+            }
+            Operation::Drop(maybe_n) => {
+                let n = match maybe_n {
+                    Some(n) => *n,
+                    None => vm.pop_count()?,
+                };
+                match unsafe { vm.obj_stack.pop_n(n) } {
+                    Ok(_) => Ok(()),
+                    Err(e) => vm.error(e),
+                }
+            }
+            Operation::Break | Operation::Continue | Operation::BreakIf | Operation::ContinueIf => {
+                let leave = if let Operation::Break | Operation::Continue = self {
+                    true
                 } else {
-                    vm.error(ProgramErrorKind::TypeError(ObjectKind::Iterator, *kind))?
+                    let b = match vm.obj_stack.pop() {
+                        Ok(t) => t,
+                        Err(_) => return vm.error(ProgramErrorKind::StackError(1)),
+                    };
+                    let ObjectData::Bool(bol) = b.data else {
+                        return vm.error(ProgramErrorKind::TypeError(ObjectKind::Bool, b.kind));
+                    };
+                    bol
+                };
+                if leave {
+                    let unwind = match self {
+                        Operation::Break | Operation::BreakIf => Unwind::Break,
+                        _ => Unwind::Continue,
+                    };
+                    vm.unwind_to_loop(unwind)?;
                 }
                 Ok(())
-            },
-            Operation::DoIf => {
-                let b = {
-                    match { vm.obj_stack.pop() } {
-                        Ok(t) => Ok(t),
-                        Err(_) => vm.error(ProgramErrorKind::StackError(1)),
-                    }
-                }?;
-                assert_eq!(b.kind, ObjectKind::Bool, "Object is not a boolean");
-                if let ObjectData::Bool(bol) = b.data {
-                    if bol {
-                        let frame = {
-                            match { vm.call_stack.last() } {
-                                Ok(t) => Ok(t),
-                                Err(_) => vm.error(ProgramErrorKind::StackError(1)),
-                            }
-                        }?;
-                        let mut new_frame = Frame::new(vm.counter, FrameKind::DoIfBlock);
-                        new_frame.copy_locals(frame);
-                        vm.call_stack.push(new_frame);
-                        vm.run_block(FrameKind::DoIfBlock);
-                    } else {
-                        let done_address = vm.program.get_done(&(vm.counter - 1));
-                        match done_address {
-                            Ok(addy) => vm.goto(*addy),
-                            Err(e) => vm.error(e)?,
-                        }
-                    }
+            }
+            Operation::Else => {
+                // the end of the true branch, the `done` pops its frame
+                let do_if = match vm.call_stack.last() {
+                    Ok(frame) => frame.return_address - 1,
+                    Err(e) => return vm.error(e),
+                };
+                match vm.program.get_done(&do_if) {
+                    Ok(addy) => vm.goto(*addy),
+                    Err(e) => vm.error(e)?,
                 }
                 Ok(())
             }
@@ -1079,6 +1149,29 @@ impl Operation {
                 // println!("call stack: {:?}", frames);
                 Ok(())
             }
+            Operation::Label(_) => Ok(()),
+            Operation::Jump(_) | Operation::JumpIf(_) | Operation::JumpUnless(_) => {
+                let jump = if let Operation::Jump(_) = self {
+                    true
+                } else {
+                    let b = match vm.obj_stack.pop() {
+                        Ok(t) => t,
+                        Err(_) => return vm.error(ProgramErrorKind::StackError(1)),
+                    };
+                    let ObjectData::Bool(bol) = b.data else {
+                        return vm.error(ProgramErrorKind::TypeError(ObjectKind::Bool, b.kind));
+                    };
+                    bol == matches!(self, Operation::JumpIf(_))
+                };
+                if jump {
+                    // the jump already moved the counter past itself
+                    match vm.program.get_jump(&(vm.counter - 1)) {
+                        Ok(label) => vm.goto(*label),
+                        Err(e) => vm.error(e)?,
+                    }
+                }
+                Ok(())
+            }
             Operation::Import(bytes) => {
                 // if MODULES.contains(bytes) {
                 //     if vm.debug {
@@ -1101,3 +1194,247 @@ impl Operation {
         }
     }
 }
+
+/// Runs the block once for every count up to the `Integer` on top of the stack.
+fn do_for(vm: &mut VM) -> Result<(), ProgramError> {
+    let object = {
+        match { vm.obj_stack.pop() } {
+            Ok(t) => Ok(t),
+            Err(e) => vm.error(e),
+        }
+    }?;
+    if let ObjectData::Integer(times) = object.data {
+        let pc = vm.counter.clone();
+        let last_frame = match vm.call_stack.last() {
+            Ok(it) => it,
+            Err(err) => vm.error(err)?,
+        };
+        let mut new_frame = Frame::new(pc, FrameKind::DoForLoop);
+        new_frame.copy_locals(last_frame);
+        vm.call_stack.push(new_frame.clone());
+        for _ in 0..times {
+            vm.counter = pc;
+            vm.start_pass();
+            vm.run_block(FrameKind::DoForLoop)?;
+            if vm.returning() {
+                return Ok(());
+            }
+            if vm.pass_ended_loop() {
+                break;
+            }
+        }
+        let _ = vm.call_stack.pop();
+        let done_address = vm.program.get_done(&(pc - 1));
+        match done_address {
+            Ok(addy) => vm.goto(*addy + 1),
+            Err(e) => vm.error(e)?,
+        }
+    }
+    Ok(())
+}
+
+/// Runs the block once for every item of the list or iterator in `obj_name`.
+fn do_for_in(vm: &mut VM, obj_name: &'static [u8]) -> Result<(), ProgramError> {
+    let current_frame = {
+        match vm.call_stack.last() {
+            Ok(ts) => Ok(ts),
+            Err(_) => vm.error(ProgramErrorKind::StackError(1)),
+        }
+    }?;
+    let Some(obj_ptr) = current_frame.get_local(obj_name) else {
+        return vm.error(ProgramErrorKind::VariableExists(obj_name));
+    };
+    let pc = vm.counter.clone();
+    let mut new_frame = Frame::new(pc, FrameKind::DoForInLoop);
+    new_frame.copy_locals(current_frame);
+    vm.call_stack.push(new_frame);
+    match obj_ptr.as_tuple() {
+        (ObjectKind::List, ObjectData::List(_start, len)) => unsafe {
+            for _ in 0..*len {
+                vm.counter = pc;
+                vm.start_pass();
+                vm.run_block(FrameKind::DoForInLoop)?;
+                if vm.returning() {
+                    return Ok(());
+                }
+                if vm.pass_ended_loop() {
+                    break;
+                }
+            }
+        },
+        (ObjectKind::Iterator, ObjectData::Iterator(list_ptr, _next)) => unsafe {
+            let list = *list_ptr;
+            if let ObjectData::List(_start, len) = list {
+                for _ in 0..*len {
+                    vm.counter = pc;
+                    vm.start_pass();
+                    vm.run_block(FrameKind::DoForInLoop)?;
+                    if vm.returning() {
+                        return Ok(());
+                    }
+                    if vm.pass_ended_loop() {
+                        break;
+                    }
+                }
+            }
+        },
+
+        (kind, _data) => return vm.error(ProgramErrorKind::TypeError(ObjectKind::List, kind)),
+    }
+    let _ = vm.call_stack.pop();
+    let done_address = vm.program.get_done(&(pc - 1));
+    match done_address {
+        Ok(addy) => vm.goto(*addy + 1),
+        Err(e) => vm.error(e)?,
+    }
+    Ok(())
+}
+
+/// Runs the block for every item left in the iterator on top of the stack.
+fn iterate(vm: &mut VM) -> Result<(), ProgramError> {
+    unsafe {
+        let Object { kind, data } = {
+            match { vm.obj_stack.pop_mut() } {
+                Ok(&mut t) => Ok(t),
+                Err(_) => vm.error(ProgramErrorKind::StackError(1)),
+            }
+        }?;
+        if let ObjectData::Iterator(list_ptr, next) = data {
+            let list = **list_ptr;
+            if let ObjectData::List(start, len) = list {
+                if *len != 0 && **next < *len {
+                    let start = *start as *const Object;
+                    let pc = vm.counter.clone();
+                    let last_frame = match vm.call_stack.last() {
+                        Ok(it) => it,
+                        Err(err) => vm.error(err)?,
+                    };
+                    let mut new_frame = Frame::new(pc, FrameKind::IterateLoop);
+                    new_frame.copy_locals(last_frame);
+                    vm.call_stack.push(new_frame);
+
+                    for n in (**next)..*len {
+                        **next = n + 1;
+                        vm.counter = pc;
+                        vm.start_pass();
+                        // println!("iterate: {}, pc: {pc}", &*start.add(n));
+                        vm.obj_stack.push(&*start.add(n));
+
+                        vm.run_block(FrameKind::IterateLoop)?;
+                        if vm.returning() {
+                            return Ok(());
+                        }
+                        if vm.pass_ended_loop() {
+                            break;
+                        }
+                    }
+                    let _ = vm.call_stack.pop();
+                    let done_address = vm.program.get_done(&(pc - 1));
+                    match done_address {
+                        Ok(addy) => vm.goto(*addy + 1),
+                        Err(e) => vm.error(e)?,
+                    }
+                } else {
+                    // past the `done`, which would pop a frame this loop never pushed
+                    let done_address = vm.program.get_done(&(vm.counter - 1));
+                    match done_address {
+                        Ok(addy) => vm.goto(*addy + 1),
+                        Err(e) => vm.error(e)?,
+                    }
+                }
+            }
+        } else {
+            vm.error(ProgramErrorKind::TypeError(ObjectKind::Iterator, *kind))?
+        }
+        Ok(())
+    }
+}
+
+/// Runs the block, or its `else` branch, depending on the `Bool` on top of the stack.
+fn do_if(vm: &mut VM) -> Result<(), ProgramError> {
+    let b = {
+        match { vm.obj_stack.pop() } {
+            Ok(t) => Ok(t),
+            Err(_) => vm.error(ProgramErrorKind::StackError(1)),
+        }
+    }?;
+    if b.kind != ObjectKind::Bool {
+        return vm.error(ProgramErrorKind::TypeError(ObjectKind::Bool, b.kind));
+    }
+    if let ObjectData::Bool(bol) = b.data {
+        let do_if = vm.counter - 1;
+        let else_address = vm.program.block_branches.get(&do_if).copied();
+        if bol || else_address.is_some() {
+            let frame = {
+                match { vm.call_stack.last() } {
+                    Ok(t) => Ok(t),
+                    Err(_) => vm.error(ProgramErrorKind::StackError(1)),
+                }
+            }?;
+            let mut new_frame = Frame::new(vm.counter, FrameKind::DoIfBlock);
+            new_frame.copy_locals(frame);
+            vm.call_stack.push(new_frame);
+            if let (false, Some(else_address)) = (bol, else_address) {
+                vm.goto(else_address + 1);
+            }
+            vm.run_block(FrameKind::DoIfBlock)?;
+        } else {
+            // past the `done`, which would pop a frame this block never pushed
+            let done_address = vm.program.get_done(&do_if);
+            match done_address {
+                Ok(addy) => vm.goto(*addy + 1),
+                Err(e) => vm.error(e)?,
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Runs the condition up to its `check`, then the block, for as long as it's `true`.
+fn do_while(vm: &mut VM) -> Result<(), ProgramError> {
+    let pc = vm.counter;
+    let (check, done) = match (
+        vm.program.block_branches.get(&(pc - 1)),
+        vm.program.get_done(&(pc - 1)),
+    ) {
+        (Some(check), Ok(done)) => (*check, *done),
+        (_, Err(e)) => return vm.error(e),
+        (None, _) => return vm.error(ProgramErrorKind::DoneAddress),
+    };
+    let last_frame = match vm.call_stack.last() {
+        Ok(it) => it,
+        Err(err) => vm.error(err)?,
+    };
+    let mut new_frame = Frame::new(pc, FrameKind::DoWhileLoop);
+    new_frame.copy_locals(last_frame);
+    vm.call_stack.push(new_frame);
+    loop {
+        vm.counter = pc;
+        vm.start_pass();
+        vm.run_until(check)?;
+        if vm.returning() {
+            return Ok(());
+        }
+        let b = match vm.obj_stack.pop() {
+            Ok(t) => t,
+            Err(_) => return vm.error(ProgramErrorKind::StackError(1)),
+        };
+        let ObjectData::Bool(bol) = b.data else {
+            return vm.error(ProgramErrorKind::TypeError(ObjectKind::Bool, b.kind));
+        };
+        if !bol {
+            break;
+        }
+        vm.counter = check + 1;
+        vm.run_block(FrameKind::DoWhileLoop)?;
+        if vm.returning() {
+            return Ok(());
+        }
+        if vm.pass_ended_loop() {
+            break;
+        }
+    }
+    let _ = vm.call_stack.pop();
+    vm.goto(done + 1);
+    Ok(())
+}
@@ -0,0 +1,192 @@
+use std::{
+    collections::HashMap,
+    fmt::Write as _,
+    fs,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use jed::{bytes_to_string, FrameKind, Hook, Operation, VM};
+
+// stands in for the main frame, which has no function index of its own
+const MAIN: usize = usize::MAX;
+
+#[derive(Default, Clone, Copy)]
+struct Counter {
+    count: u64,
+    time: Duration,
+}
+
+impl Counter {
+    fn add(&mut self, count: u64, time: Duration) {
+        self.count += count;
+        self.time += time;
+    }
+}
+
+/// Counts instructions and wall time per opcode and per function.
+///
+/// An instruction's time is whatever passes between its `before_op` and the
+/// next one, so it includes everything the instruction did, loops and calls
+/// excepted since those run their own instructions. Functions are told apart
+/// by the `Call` frames on the call stack.
+pub struct Profiler {
+    folded_path: PathBuf,
+    ops: HashMap<&'static str, Counter>,
+    stacks: HashMap<Vec<usize>, Counter>,
+    // the instruction that is running right now, with its call path and start
+    current: Option<(&'static str, Vec<usize>, Instant)>,
+}
+
+impl Profiler {
+    /// `folded_path` is where the folded stacks get written after the run.
+    pub fn new(folded_path: PathBuf) -> Self {
+        Profiler {
+            folded_path,
+            ops: HashMap::new(),
+            stacks: HashMap::new(),
+            current: None,
+        }
+    }
+
+    fn finish_current(&mut self) {
+        if let Some((name, path, start)) = self.current.take() {
+            let elapsed = start.elapsed();
+            self.ops.entry(name).or_default().add(1, elapsed);
+            self.stacks.entry(path).or_default().add(1, elapsed);
+        }
+    }
+
+    fn func_name(vm: &VM, idx: usize) -> String {
+        if idx == MAIN {
+            return "main".to_owned();
+        }
+        vm.program
+            .func_name(idx)
+            .map_or("?".to_owned(), bytes_to_string)
+    }
+
+    fn summary(&self, vm: &VM) -> String {
+        let mut out = String::new();
+        let total = self.ops.values().fold(Counter::default(), |mut acc, c| {
+            acc.add(c.count, c.time);
+            acc
+        });
+        let _ = writeln!(
+            out,
+            "profile: {} instructions in {}",
+            total.count,
+            millis(total.time)
+        );
+        let _ = writeln!(
+            out,
+            "memo: {} hits, {} misses",
+            vm.program.memo_hits, vm.program.memo_misses
+        );
+
+        let mut ops: Vec<(&&str, &Counter)> = self.ops.iter().collect();
+        ops.sort_by(|a, b| b.1.time.cmp(&a.1.time).then(a.0.cmp(b.0)));
+        let _ = writeln!(out, "\n{:<16} {:>12} {:>12}", "opcode", "count", "time");
+        for (name, counter) in ops {
+            let _ = writeln!(
+                out,
+                "{name:<16} {:>12} {:>12}",
+                counter.count,
+                millis(counter.time)
+            );
+        }
+
+        // self is the innermost function of a stack, total is every function in it
+        let mut funcs: HashMap<usize, (Counter, Counter)> = HashMap::new();
+        for (path, counter) in &self.stacks {
+            if let Some(innermost) = path.last() {
+                funcs
+                    .entry(*innermost)
+                    .or_default()
+                    .0
+                    .add(counter.count, counter.time);
+            }
+            let mut seen: Vec<usize> = vec![];
+            for idx in path {
+                if !seen.contains(idx) {
+                    seen.push(*idx);
+                    funcs
+                        .entry(*idx)
+                        .or_default()
+                        .1
+                        .add(counter.count, counter.time);
+                }
+            }
+        }
+        let mut funcs: Vec<(String, (Counter, Counter))> = funcs
+            .into_iter()
+            .map(|(idx, counters)| (Self::func_name(vm, idx), counters))
+            .collect();
+        funcs.sort_by(|a, b| b.1 .1.time.cmp(&a.1 .1.time).then(a.0.cmp(&b.0)));
+        let _ = writeln!(
+            out,
+            "\n{:<16} {:>12} {:>12} {:>12}",
+            "function", "instructions", "self", "total"
+        );
+        for (name, (own, all)) in funcs {
+            let _ = writeln!(
+                out,
+                "{name:<16} {:>12} {:>12} {:>12}",
+                own.count,
+                millis(own.time),
+                millis(all.time)
+            );
+        }
+        out
+    }
+
+    /// One `main;caller;callee nanoseconds` line per distinct call path, the
+    /// format flamegraph.pl and inferno take.
+    fn folded(&self, vm: &VM) -> String {
+        let mut lines: Vec<String> = self
+            .stacks
+            .iter()
+            .map(|(path, counter)| {
+                let names: Vec<String> = path.iter().map(|idx| Self::func_name(vm, *idx)).collect();
+                format!("{} {}", names.join(";"), counter.time.as_nanos())
+            })
+            .collect();
+        lines.sort();
+        let mut out = lines.join("\n");
+        out.push('\n');
+        out
+    }
+}
+
+impl Hook for Profiler {
+    fn before_op(&mut self, vm: &VM, op: Operation) {
+        self.finish_current();
+        let path = vm
+            .call_stack()
+            .iter()
+            .filter_map(|frame| match frame.kind {
+                FrameKind::Main => Some(MAIN),
+                FrameKind::Call => Some(frame.function()),
+                _ => None,
+            })
+            .collect();
+        // started last so the bookkeeping above isn't billed to the instruction
+        self.current = Some((op.name(), path, Instant::now()));
+    }
+
+    fn after_run(&mut self, vm: &VM) {
+        self.finish_current();
+        eprint!("{}", self.summary(vm));
+        match fs::write(&self.folded_path, self.folded(vm)) {
+            Ok(()) => eprintln!("\nfolded stacks written to {}", self.folded_path.display()),
+            Err(e) => eprintln!(
+                "\ncouldn't write folded stacks to {}: {e}",
+                self.folded_path.display()
+            ),
+        }
+    }
+}
+
+fn millis(time: Duration) -> String {
+    format!("{:.3}ms", time.as_secs_f64() * 1000.0)
+}
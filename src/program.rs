@@ -1,27 +1,69 @@
 use std::{
     collections::{BTreeMap, HashMap},
+    fmt::Write as _,
     fs::File,
     io::{self, BufReader, Read, Write},
 };
 
 use crate::{
-    arena::Dropless, error::ProgramErrorKind, object::Object, operation::Operation, utils,
+    arena::Dropless,
+    binops::BinOpKind,
+    builtin::BuiltIn,
+    error::{ParseError, ParseErrorKind, ProgramErrorKind},
+    literal::{self, Literal},
+    object::Object,
+    operation::Operation,
+    span::SourceLocation,
+    utils,
     MAGIC_NUMBER,
 };
 
 type Arity = usize;
 type Index = usize;
 
-pub type MemoKey = (Index, &'static [Object]);
+/// The labels of one block and the jumps in it waiting to be resolved.
+#[derive(Default)]
+struct BlockLabels {
+    labels: HashMap<&'static [u8], Index>,
+    jumps: Vec<(Index, &'static [u8])>,
+}
+
+impl BlockLabels {
+    fn resolve(self, targets: &mut BTreeMap<Index, Index>, problems: &mut Vec<(Index, ParseErrorKind)>) {
+        for (pc, name) in self.jumps {
+            if let Some(label) = self.labels.get(name) {
+                targets.insert(pc, *label);
+            } else {
+                let name = utils::bytes_to_string(name);
+                problems.push((pc, ParseErrorKind::UnknownLabel(name)));
+            }
+        }
+    }
+}
+
+pub(crate) type MemoKey = (Index, &'static [Object]);
 type MemoTable = HashMap<MemoKey, Object>;
 
 pub struct Program {
-    pub string_arena: Dropless,
+    pub(crate) string_arena: Dropless,
     pub saved_strings: BTreeMap<String, &'static [u8]>,
     pub instructions: Vec<Operation>,
     pub funcs: BTreeMap<&'static [u8], (Index, Arity)>,
     pub block_returns: BTreeMap<Index, Index>,
-    pub memos: MemoTable,
+    // pc of every `do_if` with an `else` to the pc of the `else`, and of every
+    // `do_while` to the pc of its `check`
+    pub block_branches: BTreeMap<Index, Index>,
+    // pc of every jump to the pc of its label
+    pub jump_targets: BTreeMap<Index, Index>,
+    // values of the `push_lit` arguments that are literals rather than const names
+    pub(crate) literals: BTreeMap<&'static [u8], Literal>,
+    pub(crate) memos: MemoTable,
+    pub memo_hits: usize,
+    pub memo_misses: usize,
+    // location of every instruction parsed from string Jed Bytecode, indexed by pc
+    pub(crate) source_map: Vec<SourceLocation>,
+    // file name that goes in the source map
+    pub source_name: &'static [u8],
 }
 
 impl Program {
@@ -32,23 +74,48 @@ impl Program {
             instructions: vec![],
             funcs: BTreeMap::new(),
             memos: HashMap::new(),
+            memo_hits: 0,
+            memo_misses: 0,
+            source_map: vec![],
+            source_name: &[],
             block_returns: BTreeMap::new(),
+            block_branches: BTreeMap::new(),
+            jump_targets: BTreeMap::new(),
+            literals: BTreeMap::new(),
         };
         // register keywords/stuff that not be added later
         // probably should be a macro but (:
         program.register("main".to_owned());
+        program.source_name = program.register("<string>".to_owned());
 
         return program;
     }
 
-    pub fn get_main(&self) -> Index {
-        let main = self.saved_strings.get("main").unwrap();
-        let (idx, _) = self
-            .funcs
-            .get(main)
-            .unwrap_or_else(|| panic!("No main func!"));
-        return *idx;
+    pub fn get_main(&self) -> Result<Index, ProgramErrorKind> {
+        match self.funcs.get("main".as_bytes()) {
+            Some((idx, _)) => Ok(*idx),
+            None => Err(ProgramErrorKind::NoMain),
+        }
+    }
+    /// Name of the function whose `func` header sits at `idx`.
+    pub fn func_name(&self, idx: Index) -> Option<&'static [u8]> {
+        self.funcs
+            .iter()
+            .find(|(_, (func_idx, _))| *func_idx == idx)
+            .map(|(name, _)| *name)
+    }
+
+    /// Name of the innermost function whose block contains `pc`.
+    pub fn func_containing(&self, pc: Index) -> Option<&'static [u8]> {
+        self.funcs
+            .iter()
+            .filter(|(_, (idx, _))| {
+                *idx <= pc && self.block_returns.get(idx).is_some_and(|done| pc <= *done)
+            })
+            .max_by_key(|(_, (idx, _))| *idx)
+            .map(|(name, _)| *name)
     }
+
     pub fn get_op(&self, idx: usize) -> &Operation {
         match self.instructions.get(idx) {
             Some(op) => op,
@@ -56,10 +123,24 @@ impl Program {
         }
     }
 
+    /// Parses a `push_lit` argument into `literals`, unless it's a const name.
+    pub fn add_literal(&mut self, text: &'static [u8]) -> Result<(), String> {
+        if self.literals.contains_key(text) {
+            return Ok(());
+        }
+        if let Some(object) = literal::parse(self, &utils::bytes_to_string(text))? {
+            self.literals.insert(text, object);
+        }
+        Ok(())
+    }
+
     pub fn register_bytes(&mut self, byte_str: &[u8]) -> &'static [u8] {
         let string = utils::bytes_to_string(byte_str);
         if let Some(saved) = self.saved_strings.get(&string) {
             return saved;
+        } else if string.is_empty() {
+            // the arena refuses zero-length slices
+            return &[];
         } else {
             let byte_str: &[u8] = self.string_arena.alloc_slice(byte_str);
             let byte_str: &'static [u8] = unsafe { &*(byte_str as *const [u8]) };
@@ -68,9 +149,22 @@ impl Program {
         }
     }
 
+    /// Forgets the instructions from `pc` on, with their blocks, jumps and
+    /// source locations, like the repl does with an entry that didn't parse.
+    pub fn truncate(&mut self, pc: Index) {
+        self.instructions.truncate(pc);
+        self.source_map.truncate(pc);
+        self.block_returns.retain(|block, _| *block < pc);
+        self.block_branches.retain(|block, _| *block < pc);
+        self.jump_targets.retain(|jump, _| *jump < pc);
+    }
+
     pub fn register(&mut self, string: String) -> &'static [u8] {
         if let Some(saved) = self.saved_strings.get(&string) {
             return saved;
+        } else if string.is_empty() {
+            // the arena refuses zero-length slices
+            return &[];
         } else {
             let byte_str = string.as_bytes();
             let byte_str: &[u8] = self.string_arena.alloc_slice(byte_str);
@@ -86,8 +180,13 @@ impl Program {
         saved_bytes
     }
 
-    pub fn get_memo(&self, key: MemoKey) -> Option<&Object> {
-        self.memos.get(&key)
+    pub fn get_memo(&mut self, key: MemoKey) -> Option<Object> {
+        let memo = self.memos.get(&key).copied();
+        match memo {
+            Some(_) => self.memo_hits += 1,
+            None => self.memo_misses += 1,
+        }
+        memo
     }
     pub fn set_memo(&mut self, key: MemoKey, result: Object) {
         self.memos.insert(key, result);
@@ -110,7 +209,11 @@ impl Program {
                 | Operation::ReturnIfConst(items)
                 | Operation::StoreConst(items)
                 | Operation::StoreName(items)
-                | Operation::DoForIn(items) => {
+                | Operation::DoForIn(items)
+                | Operation::Label(items)
+                | Operation::Jump(items)
+                | Operation::JumpIf(items)
+                | Operation::JumpUnless(items) => {
                     // op, usize (len), slice
                     let mut data = Vec::<u8>::from(&[op.into()]);
                     data.extend_from_slice(items.len().to_be_bytes().as_slice());
@@ -127,7 +230,9 @@ impl Program {
                 }
                 Operation::CreateList(option)
                 | Operation::ListGet(option)
-                | Operation::ListSet(option) => {
+                | Operation::ListSet(option)
+                | Operation::Pick(option)
+                | Operation::Drop(option) => {
                     // op, ok || none, maybe usize
                     let mut data = Vec::<u8>::from(&[op.into(), u8::from(option.is_some())]);
                     match option {
@@ -156,16 +261,45 @@ impl Program {
     ///  ...
     /// ]
     /// Spans will be added later for error reporting
-    pub fn from_file(file: &mut File) -> io::Result<Self> {
-        let mut reader = BufReader::new(file);
+    /// Reads Bytecode (`.jbc`) from `file`.
+    pub fn from_file(file: &mut File) -> Result<Self, ParseError> {
+        Self::from_bytecode(file)
+    }
+
+    /// Either Bytecode or string Jed Bytecode, told apart by the magic number.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ParseError> {
+        if bytes.starts_with(MAGIC_NUMBER) {
+            return Self::from_bytecode(bytes);
+        }
+        match std::str::from_utf8(bytes) {
+            Ok(text) => Self::from_string(text),
+            Err(_) => Err(ParseError(ParseErrorKind::NotUtf8, 0)),
+        }
+    }
+
+    /// Reads all of `reader` and parses it like `from_bytes`.
+    pub fn from_reader<R: Read>(mut reader: R) -> Result<Self, ParseError> {
+        let mut bytes = vec![];
+        if let Err(e) = reader.read_to_end(&mut bytes) {
+            return Err(ParseError(ParseErrorKind::Io(e.to_string()), 0));
+        }
+        Self::from_bytes(&bytes)
+    }
+
+    fn from_bytecode<R: Read>(reader: R) -> Result<Self, ParseError> {
+        let mut reader = BufReader::new(reader);
         let mut program = Self::new();
         let mut magic_number: [u8; 3] = [0; 3];
-        reader.read(&mut magic_number[..])?;
-        assert_eq!(magic_number, MAGIC_NUMBER, "not a jed file");
+        if reader.read_exact(&mut magic_number[..]).is_err() || magic_number != MAGIC_NUMBER {
+            return Err(ParseError(ParseErrorKind::NotBytecode, 0));
+        }
         let mut op_buffer: [u8; 1] = [0; 1];
         loop {
-            let n = reader.read(&mut op_buffer[..])?;
-            // println!("buf: {:?} ({})", op_buffer, n);
+            let pc = program.instructions.len();
+            let n = match reader.read(&mut op_buffer[..]) {
+                Ok(n) => n,
+                Err(e) => return Err(ParseError(ParseErrorKind::Io(e.to_string()), pc)),
+            };
             if n != 1 {
                 break;
             }
@@ -174,54 +308,46 @@ impl Program {
                 // BinOpKind
                 // in file: u8
                 1 => {
-                    let mut binopbuffer: [u8; 1] = [0; 1];
-                    reader.read(&mut binopbuffer[..])?;
-                    program
-                        .instructions
-                        .push(Operation::BinOp(binopbuffer[0].into()))
+                    let code = read_bytes::<_, 1>(&mut reader, pc)?[0];
+                    match BinOpKind::from_code(code) {
+                        Some(kind) => program.instructions.push(Operation::BinOp(kind)),
+                        None => return Err(bad_bytecode(format!("no binary operator {code}"), pc)),
+                    }
                 }
                 // "call_builtin"
                 // BuiltIn
                 // in file: u8
                 3 => {
-                    let mut builtinbuffer: [u8; 1] = [0; 1];
-                    reader.read(&mut builtinbuffer[..])?;
-                    program
-                        .instructions
-                        .push(Operation::CallBuiltIn(builtinbuffer[0].into()))
+                    let code = read_bytes::<_, 1>(&mut reader, pc)?[0];
+                    match BuiltIn::from_code(code) {
+                        Some(built_in) => program.instructions.push(Operation::CallBuiltIn(built_in)),
+                        None => return Err(bad_bytecode(format!("no builtin {code}"), pc)),
+                    }
                 }
                 // "call", "push_lit", "push_name", "return_if", "store_const",
-                // "store_name", "do_for_in", "return_if_const"
+                // "store_name", "do_for_in", "return_if_const", "label", "jump",
+                // "jump_if", "jump_unless"
                 // &'static [u8]
                 // in file: usize (length), [u8; length]
-                2 | 4 | 5 | 8 | 9 | 10 | 16 | 22 => {
-                    let mut slice_length: [u8; size_of::<usize>()] = [0; size_of::<usize>()];
-                    let n = reader.read(&mut slice_length[..])?;
-                    assert_eq!(n, size_of::<usize>(), "did not receive enough data");
-                    let slice_length: usize = usize::from_be_bytes(slice_length);
-                    println!("slice_length {}", slice_length);
-
-                    let mut args: Vec<u8> = vec![0; slice_length];
-                    let n = reader.read(&mut args)?;
-                    assert_eq!(n, slice_length, "did not receive enough data");
+                2 | 4 | 5 | 8 | 9 | 10 | 16 | 22 | 35..=38 => {
+                    let args = read_slice(&mut reader, pc)?;
                     let args = program.register_bytes(&args);
+                    if op_buffer[0] == 4 {
+                        if let Err(reason) = program.add_literal(args) {
+                            return Err(bad_bytecode(reason, pc));
+                        }
+                    }
 
                     program.instructions.push((op_buffer[0], args).into());
                 }
 
-                // create_list, list_get, list_set
+                // create_list, list_get, list_set, pick, drop
                 // Option<usize>
                 // in file: Bool, usize
-                17 | 19 | 20 => {
-                    let mut boolean: [u8; 1] = [0; 1];
-                    let n = reader.read(&mut boolean[..])?;
-                    assert_eq!(n, 1, "did not receive enough data");
-                    let boolean: bool = unsafe { std::mem::transmute(boolean) };
+                17 | 19 | 20 | 51 | 52 => {
+                    let boolean = read_bytes::<_, 1>(&mut reader, pc)?[0] != 0;
                     if boolean {
-                        let mut number: [u8; size_of::<usize>()] = [0; size_of::<usize>()];
-                        let n = reader.read(&mut number[..])?;
-                        assert_eq!(n, size_of::<usize>(), "did not receive enough data");
-                        let number: usize = usize::from_be_bytes(number);
+                        let number = read_usize(&mut reader, pc)?;
                         program
                             .instructions
                             .push((op_buffer[0], Some(number)).into())
@@ -233,136 +359,184 @@ impl Program {
                 // &'static [u8], usize
                 // in file: usize (length), [u8; length]
                 12 => {
-                    let mut slice_length: [u8; size_of::<usize>()] = [0; size_of::<usize>()];
-                    let n = reader.read(&mut slice_length[..])?;
-                    assert_eq!(n, size_of::<usize>(), "did not receive enough data");
-                    let slice_length: usize = usize::from_be_bytes(slice_length);
-
-                    let mut name: Vec<u8> = vec![0; slice_length];
-                    let n = reader.read(&mut name)?;
-                    assert_eq!(n, slice_length, "did not receive enough data");
+                    let name = read_slice(&mut reader, pc)?;
                     let name = program.register_bytes(&name);
-
-                    let mut arity: [u8; size_of::<usize>()] = [0; size_of::<usize>()];
-                    let n = reader.read(&mut arity[..])?;
-                    assert_eq!(n, size_of::<usize>(), "did not receive enough data");
-                    let arity: usize = usize::from_be_bytes(arity);
-
-                    program.instructions.push(Operation::Func(name, arity));
+                    let arity = read_usize(&mut reader, pc)?;
 
                     // register the function
                     program
                         .funcs
                         .insert(name, (program.instructions.len(), arity));
+
+                    program.instructions.push(Operation::Func(name, arity));
                 }
 
                 // push_temp, pop, store_temp, done, exit, do_for, list_push, push_range,
                 // get_ptr, read_ptr, set_ptr, get_iter, iter_next, iter_prev, iter_skip,
                 // iter_current, iterate, do_if, debug
                 6 | 7 | 11 | 13 | 14 | 15 | 18 | 21 | 23 | 24 | 25 | 26 | 27 | 28 | 29 | 30
-                | 31 | 32 | 33 => program.instructions.push(op_buffer[0].into()),
-                _ => break,
+                | 31 | 32 | 33 | 39..=50 | 53 | 54 => program.instructions.push(op_buffer[0].into()),
+                code => return Err(bad_bytecode(format!("no operation {code}"), pc)),
             }
         }
-        // Get the Done address for each block
-        let blocks: Vec<(usize, &Operation)> = program
-            .instructions
-            .iter()
+        if let Some((pc, kind)) = program.match_blocks(0).into_iter().next() {
+            return Err(bad_bytecode(kind.to_string(), pc));
+        }
+        if let Some((pc, name)) = program.undefined_calls(0).into_iter().next() {
+            let kind = ParseErrorKind::UndefinedFunction(utils::bytes_to_string(name));
+            return Err(bad_bytecode(kind.to_string(), pc));
+        }
+
+        return Ok(program);
+    }
+
+    /// Parses string Jed Bytecode, failing with the first problem in it.
+    pub fn from_string(text: &str) -> Result<Self, ParseError> {
+        let mut program = Self::new();
+        program.append_string(text)?;
+        return Ok(program);
+    }
+
+    /// Like `from_string`, with `name` as the file name in the source map.
+    pub fn from_source(name: &str, text: &str) -> Result<Self, ParseError> {
+        let mut program = Self::new();
+        program.source_name = program.register(name.to_owned());
+        program.append_string(text)?;
+        return Ok(program);
+    }
+
+    /// Rebuilds the source map of a program that was compiled from `text`, for
+    /// Bytecode loaded from the cache. Every operation line of `text` is one
+    /// instruction, so this only needs to split lines. Returns false and leaves
+    /// the program alone if `text` doesn't have as many instructions.
+    pub fn attach_source(&mut self, name: &str, text: &str) -> bool {
+        let lines: Vec<(usize, &str)> = text
+            .split('\n')
             .enumerate()
-            .filter(|(_, o)| match o {
-                Operation::Done | Operation::Exit => return true,
-                Operation::Func(_, _)
-                | Operation::DoFor
-                | Operation::DoForIn(_)
-                | Operation::Iterate
-                | Operation::DoIf => return true,
-                _ => return false,
-            })
+            .filter(|(_, line)| Self::split_line(line).is_some())
             .collect();
+        if lines.len() != self.instructions.len() {
+            return false;
+        }
+        self.source_name = self.register(name.to_owned());
+        self.source_map = lines
+            .into_iter()
+            .map(|(idx, line)| self.locate(idx + 1, line))
+            .collect();
+        true
+    }
 
-        assert_eq!(
-            blocks.len() % 2,
-            0,
-            "bro some block aint closed {:?}",
-            blocks
-        );
-        let mut claimed_dones: Vec<usize> = vec![];
-        for (block_pc, block_op) in blocks.iter() {
-            match block_op {
-                Operation::Func(_, _)
-                | Operation::DoFor
-                | Operation::DoForIn(_)
-                | Operation::Iterate
-                | Operation::DoIf => {
-                    for (done_pc, done_op) in blocks.iter().rev() {
-                        match done_op {
-                            Operation::Done | Operation::Exit => {
-                                if !claimed_dones.contains(done_pc) {
-                                    claimed_dones.push(*done_pc);
-                                    program.block_returns.insert(*block_pc, *done_pc);
-                                    break;
-                                }
-                            }
-                            _ => (),
-                        }
-                    }
-                }
-                _ => {}
-            }
+    fn locate(&mut self, line_no: usize, line: &str) -> SourceLocation {
+        SourceLocation {
+            file: self.source_name,
+            line: line_no,
+            column: line.len() - line.trim_start().len() + 1,
+            text: self.register(line.to_owned()),
         }
+    }
 
-        return Ok(program);
+    /// Parses string Jed Bytecode and adds it after the existing instructions.
+    ///
+    /// Blocks opened in `text` have to be closed in `text` too.
+    pub fn append_string(&mut self, text: &str) -> Result<(), ParseError> {
+        match self.parse_string(text).into_iter().next() {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
     }
-    pub fn from_string(text: String) -> Self {
-        let mut program = Self::new();
-        for line in text.split('\n') {
-            if line.trim().is_empty() {
+
+    /// Like `append_string`, but every problem in `text` is collected instead of
+    /// panicking on the first one. Lines with a problem are left out, so the
+    /// instructions are only worth running when nothing was returned.
+    pub fn parse_string(&mut self, text: &str) -> Vec<ParseError> {
+        let program = self;
+        let mut errors = vec![];
+        let start = program.instructions.len();
+        // line of every instruction pushed from `text`
+        let mut lines: Vec<usize> = vec![];
+        // names stored so far in the function being parsed, `None` outside of one
+        let mut stored: Option<Vec<String>> = None;
+
+        for (line_idx, line) in text.split('\n').enumerate() {
+            let line_no = line_idx + 1;
+            let Some((op, arg)) = Self::split_line(line) else {
                 continue;
-            }
-            let line_spl: Vec<&str> = line.split(' ').map(|x| x.trim()).collect();
-            let op = line_spl[0];
-            if op.starts_with('#') {
+            };
+            if !Operation::exists(op) {
+                errors.push(ParseError(
+                    ParseErrorKind::UnknownOperation(op.to_owned()),
+                    line_no,
+                ));
                 continue;
             }
-            assert!(Operation::exists(op), "'{}' not a valid operation", op);
-
-            let arg = line_spl[1..].join(" ");
 
             let op_code = Operation::get_opcode(op);
             let operation = match op_code {
-                1 => Operation::BinOp(arg.as_str().into()),
-                2 => {
-                    let name = program.register(arg);
-                    match program.funcs.get(name) {
-                        Some(_) => Operation::Call(name),
-                        None => panic!(
-                            "Call to nonexistent function '{}'",
-                            utils::bytes_to_string(name)
-                        ),
+                1 => match BinOpKind::parse(&arg) {
+                    Some(kind) => Operation::BinOp(kind),
+                    None => {
+                        errors.push(ParseError(ParseErrorKind::UnknownBinOp(arg), line_no));
+                        continue;
+                    }
+                },
+                // checked once every function is known, see `undefined_calls`
+                2 => Operation::Call(program.register(arg)),
+                3 => match BuiltIn::parse(&arg) {
+                    Some(built_in) => Operation::CallBuiltIn(built_in),
+                    None => {
+                        errors.push(ParseError(ParseErrorKind::UnknownBuiltIn(arg), line_no));
+                        continue;
+                    }
+                },
+                4 => {
+                    let literal = program.register(arg);
+                    if let Err(reason) = program.add_literal(literal) {
+                        errors.push(ParseError(ParseErrorKind::InvalidLiteral(reason), line_no));
+                        continue;
                     }
+                    Operation::PushLit(literal)
                 }
-                3 => Operation::CallBuiltIn(arg.as_str().into()),
-                4 => Operation::PushLit(program.register(arg)),
                 5 => Operation::PushName(program.register(arg)),
                 6 => Operation::PushTemp,
                 7 => Operation::Pop,
                 8 => Operation::ReturnIf(program.register(arg)),
                 9 => Operation::StoreConst(program.register(arg)),
-                10 => Operation::StoreName(program.register(arg)),
+                10 => {
+                    if let Some(stored) = &mut stored {
+                        stored.push(arg.clone());
+                    }
+                    Operation::StoreName(program.register(arg))
+                }
                 11 => Operation::StoreTemp,
                 12 => {
-                    let saved_name = program.register(line_spl[1].to_owned());
-                    let arity = line_spl[2]
-                        .parse::<usize>()
-                        .expect("arity is not a number or something");
+                    let line_spl: Vec<&str> = arg.split_whitespace().collect();
+                    // a bare `func` is reported as a bad arity just below
+                    let saved_name = program.register(line_spl.first().unwrap_or(&"").to_string());
+                    let arity = match line_spl.get(1).map(|a| a.parse::<usize>()) {
+                        Some(Ok(arity)) if line_spl.len() == 2 => arity,
+                        _ => {
+                            // still opens a block, keep it so the `done` matches
+                            errors.push(ParseError(ParseErrorKind::BadArity(arg), line_no));
+                            0
+                        }
+                    };
                     let idx = program.instructions.len();
                     program.funcs.insert(saved_name, (idx, arity));
+                    stored = Some(vec![]);
                     Operation::Func(saved_name, arity)
                 }
                 13 => Operation::Done,
                 14 => Operation::Exit,
                 15 => Operation::DoFor,
-                16 => Operation::DoForIn(program.register(arg)),
+                16 => {
+                    if stored.as_ref().is_some_and(|names| !names.contains(&arg)) {
+                        errors.push(ParseError(
+                            ParseErrorKind::UnknownName(arg.clone()),
+                            line_no,
+                        ));
+                    }
+                    Operation::DoForIn(program.register(arg))
+                }
                 17 => Operation::CreateList(utils::string_to_t(arg).ok()),
                 18 => Operation::ListPush,
                 19 => Operation::ListGet(utils::string_to_t(arg).ok()),
@@ -380,53 +554,250 @@ impl Program {
                 31 => Operation::Iterate,
                 32 => Operation::DoIf,
                 33 => Operation::Debug,
-
-                0 | _ => panic!("No such operation '{}'", op),
+                // resolved once the blocks are known, see `match_blocks`
+                35 => Operation::Label(program.register(arg)),
+                36 => Operation::Jump(program.register(arg)),
+                37 => Operation::JumpIf(program.register(arg)),
+                38 => Operation::JumpUnless(program.register(arg)),
+                39 => Operation::Else,
+                40 => Operation::DoWhile,
+                41 => Operation::Check,
+                42 => Operation::Break,
+                43 => Operation::Continue,
+                44 => Operation::BreakIf,
+                45 => Operation::ContinueIf,
+                46 => Operation::Return,
+                47 => Operation::Dup,
+                48 => Operation::Swap,
+                49 => Operation::Over,
+                50 => Operation::Rot,
+                // without a count they take it from the stack, a bad one is a mistake
+                51 | 52 => {
+                    let count = if arg.is_empty() {
+                        None
+                    } else {
+                        match arg.parse::<usize>() {
+                            Ok(count) => Some(count),
+                            Err(_) => {
+                                let kind = ParseErrorKind::BadCount(op.to_owned(), arg);
+                                errors.push(ParseError(kind, line_no));
+                                continue;
+                            }
+                        }
+                    };
+                    if op_code == 51 {
+                        Operation::Pick(count)
+                    } else {
+                        Operation::Drop(count)
+                    }
+                }
+                53 => Operation::Neg,
+                54 => Operation::Not,
+                _ => {
+                    // `import` is known, but there is nothing to run it yet
+                    errors.push(ParseError(ParseErrorKind::Unsupported(op.to_owned()), line_no));
+                    continue;
+                }
             };
+            // only when every instruction before has a location, so pcs line up
+            if program.source_map.len() == program.instructions.len() {
+                let location = program.locate(line_no, line);
+                program.source_map.push(location);
+            }
             program.instructions.push(operation);
+            lines.push(line_no);
+        }
+
+        for (pc, kind) in program.match_blocks(start) {
+            errors.push(ParseError(kind, lines[pc - start]));
         }
-        // Get the Done address for each block
-        // Also I think this is dumb?
-        // But, hey it works.
-        let blocks: Vec<(usize, &Operation)> = program
-            .instructions
+        for (pc, name) in program.undefined_calls(start) {
+            errors.push(ParseError(
+                ParseErrorKind::UndefinedFunction(utils::bytes_to_string(name)),
+                lines[pc - start],
+            ));
+        }
+        errors.sort_by_key(|e| e.1);
+        errors
+    }
+
+    /// Splits a line of string Jed Bytecode into its operation and argument,
+    /// `None` for blank lines and comments.
+    ///
+    /// The argument keeps its whitespace as written, only a trailing `# comment`
+    /// and the whitespace around it are cut off.
+    pub fn split_line(line: &str) -> Option<(&str, String)> {
+        let (code, _) = Self::split_comment(line);
+        let code = code.trim();
+        if code.is_empty() {
+            return None;
+        }
+        match code.split_once(char::is_whitespace) {
+            Some((op, arg)) => Some((op, arg.trim_start().to_owned())),
+            None => Some((code, String::new())),
+        }
+    }
+
+    /// Splits a line at the `#` that starts its comment, `#`s inside string
+    /// literals don't count.
+    pub fn split_comment(line: &str) -> (&str, Option<&str>) {
+        let mut in_string = false;
+        let mut escaped = false;
+        for (idx, c) in line.char_indices() {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' if in_string => escaped = true,
+                '"' => in_string = !in_string,
+                '#' if !in_string => return (&line[..idx], Some(&line[idx..])),
+                _ => {}
+            }
+        }
+        (line, None)
+    }
+
+    /// The `call`s from `start` on whose function isn't defined anywhere,
+    /// so functions can be called before (or from inside) their definition.
+    fn undefined_calls(&self, start: Index) -> Vec<(Index, &'static [u8])> {
+        self.instructions
             .iter()
             .enumerate()
-            .filter(|(_, o)| match o {
-                Operation::Done | Operation::Exit => return true,
-                Operation::Func(_, _)
-                | Operation::DoFor
-                | Operation::DoForIn(_)
-                | Operation::Iterate
-                | Operation::DoIf => return true,
-                _ => return false,
+            .skip(start)
+            .filter_map(|(pc, op)| match op {
+                Operation::Call(name) if !self.funcs.contains_key(name) => Some((pc, *name)),
+                _ => None,
             })
-            .collect();
+            .collect()
+    }
 
-        assert_eq!(
-            blocks.len() % 2,
-            0,
-            "bro some block aint closed {:?}",
-            blocks
-        );
-        let mut block_queue: Vec<usize> = vec![];
-        for (pc, op) in blocks.iter() {
+    /// Pairs every block opened from `start` on with its `done`/`exit` in
+    /// `block_returns`, returning the pcs of openers and closers without a partner.
+    ///
+    /// An `else` or `check` is recorded in `block_branches` for the `do_if` or
+    /// `do_while` it sits in.
+    /// Jumps are resolved along the way into `jump_targets`. A jump only sees the
+    /// labels of its own block, so it can't leave a function or skip over the
+    /// frame a block opens and closes.
+    fn match_blocks(&mut self, start: Index) -> Vec<(Index, ParseErrorKind)> {
+        let mut problems = vec![];
+        // every open block with its labels and jumps, the instructions outside any block first
+        let mut block_queue: Vec<(Index, BlockLabels)> = vec![(start, BlockLabels::default())];
+        for (pc, op) in self.instructions.iter().enumerate().skip(start) {
+            let (_, labels) = block_queue.last_mut().expect("the outermost scope is never closed");
             match op {
+                Operation::Label(name) => {
+                    if labels.labels.insert(name, pc).is_some() {
+                        let name = utils::bytes_to_string(name);
+                        problems.push((pc, ParseErrorKind::DuplicateLabel(name)));
+                    }
+                }
+                Operation::Jump(name) | Operation::JumpIf(name) | Operation::JumpUnless(name) => {
+                    labels.jumps.push((pc, name));
+                }
                 Operation::Func(_, _)
                 | Operation::DoFor
                 | Operation::DoForIn(_)
                 | Operation::Iterate
-                | Operation::DoIf => {
-                    block_queue.push(*pc);
+                | Operation::DoIf
+                | Operation::DoWhile => {
+                    block_queue.push((pc, BlockLabels::default()));
+                }
+                Operation::Else | Operation::Check => {
+                    let opener = match op {
+                        Operation::Else => "do_if",
+                        _ => "do_while",
+                    };
+                    let nested = block_queue.len() > 1;
+                    match block_queue.last_mut() {
+                        Some((block_pc, labels))
+                            if nested
+                                && self.instructions[*block_pc].name() == opener
+                                && !self.block_branches.contains_key(block_pc) =>
+                        {
+                            self.block_branches.insert(*block_pc, pc);
+                            // a loop's condition and body are run separately, a jump can't go between them
+                            if let Operation::Check = op {
+                                std::mem::take(labels).resolve(&mut self.jump_targets, &mut problems);
+                            }
+                        }
+                        _ => {
+                            let kind = ParseErrorKind::MisplacedBranch(op.to_string(), opener.to_owned());
+                            problems.push((pc, kind));
+                        }
+                    }
+                }
+                Operation::Break | Operation::Continue | Operation::BreakIf | Operation::ContinueIf => {
+                    // the innermost block that isn't a `do_if` has to be a loop, past its condition
+                    let in_loop = block_queue
+                        .iter()
+                        .skip(1)
+                        .rev()
+                        .map(|(block_pc, _)| (*block_pc, &self.instructions[*block_pc]))
+                        .find(|(_, block)| !matches!(block, Operation::DoIf))
+                        .is_some_and(|(block_pc, block)| match block {
+                            Operation::DoFor | Operation::DoForIn(_) | Operation::Iterate => true,
+                            Operation::DoWhile => self.block_branches.contains_key(&block_pc),
+                            _ => false,
+                        });
+                    if !in_loop {
+                        problems.push((pc, ParseErrorKind::OutsideLoop(op.to_string())));
+                    }
+                }
+                Operation::Done | Operation::Exit if block_queue.len() > 1 => {
+                    let (block_pc, labels) = block_queue.pop().expect("checked above");
+                    if let Operation::DoWhile = self.instructions[block_pc] {
+                        if !self.block_branches.contains_key(&block_pc) {
+                            problems.push((block_pc, ParseErrorKind::MissingCheck));
+                        }
+                    }
+                    self.block_returns.insert(block_pc, pc);
+                    labels.resolve(&mut self.jump_targets, &mut problems);
                 }
                 Operation::Done | Operation::Exit => {
-                    let block_pc = block_queue.pop().unwrap();
-                    program.block_returns.insert(block_pc, *pc);
+                    problems.push((pc, ParseErrorKind::UnmatchedClose(op.to_string())));
                 }
                 _ => {}
             }
         }
-        return program;
+        let mut block_queue = block_queue.into_iter();
+        if let Some((_, outermost)) = block_queue.next() {
+            outermost.resolve(&mut self.jump_targets, &mut problems);
+        }
+        for (block_pc, labels) in block_queue {
+            labels.resolve(&mut self.jump_targets, &mut problems);
+            let name = self.instructions[block_pc].name().to_owned();
+            problems.push((block_pc, ParseErrorKind::UnclosedBlock(name)));
+        }
+        problems
+    }
+
+    /// Turns the instructions back into string Jed Bytecode.
+    ///
+    /// Every instruction is preceded by a comment with its pc and every function by a
+    /// header comment, so the output can be fed straight back into `Program::from_string`.
+    pub fn disassemble(&self) -> String {
+        let closers: Vec<&Index> = self.block_returns.values().collect();
+        let mut out = String::new();
+        let mut depth: usize = 0;
+        for (pc, op) in self.instructions.iter().enumerate() {
+            if closers.contains(&&pc) {
+                depth = depth.saturating_sub(1);
+            }
+            let branch = self.block_branches.values().any(|branch| *branch == pc);
+            if let Operation::Func(name, arity) = op {
+                if pc != 0 {
+                    out.push('\n');
+                }
+                let end = utils::unwrap_as_string_or(self.block_returns.get(&pc), "?");
+                let name = utils::bytes_to_string(name);
+                let _ = writeln!(out, "## {name}/{arity} (pc {pc}..={end})");
+            }
+            let _ = writeln!(out, "# {pc}");
+            let _ = writeln!(out, "{}{op}", "\t".repeat(depth.saturating_sub(usize::from(branch))));
+            if self.block_returns.contains_key(&pc) {
+                depth += 1;
+            }
+        }
+        out
     }
 
     pub fn get_done(&self, pc: &usize) -> Result<&usize, ProgramErrorKind> {
@@ -436,6 +807,13 @@ impl Program {
         }
     }
 
+    pub fn get_jump(&self, pc: &usize) -> Result<&usize, ProgramErrorKind> {
+        match self.jump_targets.get(pc) {
+            Some(address) => Ok(address),
+            None => Err(ProgramErrorKind::JumpAddress),
+        }
+    }
+
     // pub fn import_module(&mut self, other: &mut Program) {
     //     // update: vm.program.instructions, vm.program.funcs, vm.program.string_arena, vm.program.saved_strings
     //     let length_of_other = other.instructions.len();
@@ -452,3 +830,141 @@ impl Program {
     //     drop(other);
     // }
 }
+
+fn bad_bytecode(reason: String, pc: Index) -> ParseError {
+    ParseError(ParseErrorKind::BadBytecode(reason), pc)
+}
+
+fn read_bytes<R: Read, const N: usize>(reader: &mut R, pc: Index) -> Result<[u8; N], ParseError> {
+    let mut buffer = [0; N];
+    match reader.read_exact(&mut buffer) {
+        Ok(()) => Ok(buffer),
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => {
+            Err(bad_bytecode("did not receive enough data".to_owned(), pc))
+        }
+        Err(e) => Err(ParseError(ParseErrorKind::Io(e.to_string()), pc)),
+    }
+}
+
+fn read_usize<R: Read>(reader: &mut R, pc: Index) -> Result<usize, ParseError> {
+    Ok(usize::from_be_bytes(read_bytes(reader, pc)?))
+}
+
+// usize (length), [u8; length]
+fn read_slice<R: Read>(reader: &mut R, pc: Index) -> Result<Vec<u8>, ParseError> {
+    let length = read_usize(reader, pc)?;
+    // the length comes from the file, so only what's actually there gets allocated
+    let mut slice = vec![];
+    match reader.take(length as u64).read_to_end(&mut slice) {
+        // names, literals and labels all come from string Jed Bytecode
+        Ok(read) if read == length && std::str::from_utf8(&slice).is_err() => {
+            Err(bad_bytecode("a name or literal isn't UTF-8".to_owned(), pc))
+        }
+        Ok(read) if read == length => Ok(slice),
+        Ok(_) => Err(bad_bytecode("did not receive enough data".to_owned(), pc)),
+        Err(e) => Err(ParseError(ParseErrorKind::Io(e.to_string()), pc)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Program;
+    use crate::error::ParseErrorKind;
+
+    #[test]
+    fn parse_string_collects_every_problem() {
+        let text = "func main 0\n\tcall_builtin nope\n\tnot_an_op\n\tcall missing\n";
+        let errors = Program::new().parse_string(text);
+        let found: Vec<(String, usize)> = errors
+            .iter()
+            .map(|e| (format!("{:?}", e.0), e.1))
+            .collect();
+        assert_eq!(found.len(), 4, "{found:?}");
+        assert!(matches!(errors[0].0, ParseErrorKind::UnclosedBlock(_)));
+        assert_eq!(errors.iter().map(|e| e.1).collect::<Vec<_>>(), [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn bad_string_literals_are_reported() {
+        let text = "func main 0\n\tpush_lit \"a\\q\"\n\tpush_lit \"open # no\n\tpush_lit \"ok\" # yes\ndone\n";
+        let errors = Program::new().parse_string(text);
+        let found: Vec<usize> = errors
+            .iter()
+            .filter(|e| matches!(e.0, ParseErrorKind::InvalidLiteral(_)))
+            .map(|e| e.1)
+            .collect();
+        assert_eq!(found, [2, 3]);
+    }
+
+    #[test]
+    fn jumps_stay_in_their_block() {
+        let text = "func main 0\n\tlabel top\n\tpush_lit true\n\tdo_if\n\t\tjump top\n\tdone\n\tjump top\ndone\n";
+        let mut program = Program::new();
+        let errors = program.parse_string(text);
+        assert_eq!(errors.len(), 1, "{errors:?}");
+        assert!(matches!(&errors[0].0, ParseErrorKind::UnknownLabel(name) if name == "top"));
+        assert_eq!(errors[0].1, 5);
+        assert_eq!(program.jump_targets.get(&6), Some(&1));
+    }
+
+    #[test]
+    fn else_belongs_to_its_do_if() {
+        let text = "func main 0\n\tpush_lit true\n\tdo_if\n\telse\n\t\tpush_lit 1\n\t\tdo_for\n\t\t\telse\n\t\tdone\n\tdone\ndone\n";
+        let mut program = Program::new();
+        let errors = program.parse_string(text);
+        assert_eq!(errors.len(), 1, "{errors:?}");
+        assert!(matches!(errors[0].0, ParseErrorKind::MisplacedBranch(_, _)));
+        assert_eq!(errors[0].1, 7);
+        assert_eq!(program.block_branches.get(&2), Some(&3));
+    }
+
+    #[test]
+    fn source_map_survives_bytecode() {
+        let text = "# comment\nfunc main 0\n\n    push_lit 1\ndone\n";
+        let program = Program::from_source("a.jed", text).expect("parses");
+        let lines: Vec<(usize, usize)> = program
+            .source_map
+            .iter()
+            .map(|loc| (loc.line, loc.column))
+            .collect();
+        assert_eq!(lines, [(2, 1), (4, 5), (5, 1)]);
+
+        let mut stripped = Program::from_string(text).expect("parses");
+        stripped.source_map.clear();
+        assert!(stripped.attach_source("a.jed", text));
+        assert_eq!(stripped.source_map[1].line, 4);
+        assert!(!stripped.attach_source("a.jed", "func main 0\ndone\n"));
+    }
+
+    #[test]
+    fn stack_op_counts_are_numbers() {
+        for bad in ["pick foo", "drop -1"] {
+            let text = format!("func main 0\n\t{bad}\ndone\n");
+            let err = Program::from_string(&text).err().map(|e| e.0);
+            assert!(matches!(err, Some(ParseErrorKind::BadCount(..))), "{bad}");
+        }
+        let program = Program::from_string("func main 0\n\tpick\n\tdrop 2\ndone\n");
+        assert!(program.is_ok());
+    }
+
+    #[test]
+    fn from_bytes_and_reader_take_text() {
+        let text = "func main 0\n\tpush_lit 1\ndone\n";
+        let from_bytes = Program::from_bytes(text.as_bytes()).expect("parses");
+        let from_reader = Program::from_reader(text.as_bytes()).expect("parses");
+        assert_eq!(from_bytes.disassemble(), from_reader.disassemble());
+        assert_eq!(from_bytes.instructions.len(), 3);
+        assert!(Program::from_bytes(&[0xff, 0xfe]).is_err());
+        let err = Program::from_string("func main 0\n\tfrob\ndone\n").err();
+        assert_eq!(err.map(|e| e.1), Some(2));
+        assert!(Program::from_bytes(b"jed\x01\xff").is_err());
+        let err = Program::from_string("func\ndone\n").err();
+        assert!(matches!(err.map(|e| e.0), Some(ParseErrorKind::BadArity(_))));
+        let err = Program::from_string("import foo\nfunc main 0\ndone\n").err();
+        assert!(matches!(err.map(|e| e.0), Some(ParseErrorKind::Unsupported(_))));
+        // a slice length far past the end of the file
+        assert!(Program::from_bytes(b"jed\x02\x7f\xff\xff\xff\xff\xff\xff\xff").is_err());
+        // a `call` whose name is the single byte 0xff
+        assert!(Program::from_bytes(b"jed\x02\0\0\0\0\0\0\0\x01\xff").is_err());
+    }
+}
@@ -0,0 +1,140 @@
+use std::io::{self, BufRead, Write};
+
+use jed::{bytes_to_string, Operation, Program, VM};
+
+const PROMPT: &str = "jed> ";
+const CONTINUE_PROMPT: &str = "...  ";
+const HELP: &str = "\
+Type string Jed Bytecode, it runs as soon as every block is closed.
+Functions (`func ... done`) are defined instead of run.
+
+  :stack   print the object stack
+  :locals  print the locals of the main frame
+  :funcs   print the defined functions
+  :help    print this message
+  :quit    leave the repl";
+
+/// Interactive loop over a single `VM`, so constants, the object stack, `temp`
+/// and the main frame's locals live on between entries.
+pub fn run(preload: Option<String>, debug: bool) -> io::Result<()> {
+    let mut program = Program::new();
+    program.source_name = program.register("<repl>".to_owned());
+    if let Some(text) = preload {
+        if let Err(e) = program.append_string(&text) {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+    }
+    let mut vm = VM::new(program, debug);
+
+    let stdin = io::stdin();
+    let mut stdout = io::stdout();
+    let mut entry = String::new();
+    let mut depth: usize = 0;
+
+    loop {
+        print!("{}", if entry.is_empty() { PROMPT } else { CONTINUE_PROMPT });
+        stdout.flush()?;
+
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            println!();
+            return Ok(());
+        }
+        let trimmed = line.trim();
+
+        if entry.is_empty() {
+            match trimmed {
+                "" => continue,
+                ":quit" | ":q" => return Ok(()),
+                ":help" => println!("{HELP}"),
+                ":stack" => print_stack(&vm),
+                ":locals" => print_locals(&vm),
+                ":funcs" => print_funcs(&vm),
+                _ if trimmed.starts_with(':') => println!("unknown command '{trimmed}'"),
+                _ => {}
+            }
+            if trimmed.starts_with(':') {
+                continue;
+            }
+        }
+
+        if let Some((op, _)) = Program::split_line(&line) {
+            if Operation::opens_block(op) {
+                depth += 1;
+            } else if op == "done" && depth == 0 {
+                println!("'done' without a block to close");
+                continue;
+            } else if Operation::closes_block(op) && depth > 0 {
+                // exit only closes a block when there is one
+                depth -= 1;
+            }
+        }
+        entry.push_str(&line);
+        if depth > 0 {
+            continue;
+        }
+
+        let text = std::mem::take(&mut entry);
+        let start = vm.program.instructions.len();
+        // a failed entry may have redefined a function, the old one has to come back
+        let funcs = vm.program.funcs.clone();
+        let errors = vm.program.parse_string(&text);
+        for e in &errors {
+            println!("{e}");
+        }
+        if !errors.is_empty() {
+            // forget the half parsed entry
+            vm.program.truncate(start);
+            vm.program.funcs = funcs;
+            continue;
+        }
+
+        // function definitions only get registered, everything else runs right away
+        if let Some(Operation::Func(_, _)) = vm.program.instructions.get(start) {
+            continue;
+        }
+        if let Err(e) = vm.run_from(start) {
+            eprintln!("runtime failure:\n{e}");
+            // drop whatever blocks and calls the failed entry was in
+            vm.leave_to_main();
+        }
+        if vm.halted {
+            return Ok(());
+        }
+    }
+}
+
+fn print_stack(vm: &VM) {
+    let objects = vm.obj_stack();
+    if objects.is_empty() {
+        println!("(empty)");
+    }
+    for (idx, obj) in objects.iter().enumerate().rev() {
+        println!("{idx:>4}  {:?}", obj.data);
+    }
+    if let Some(temp) = vm.temp {
+        println!("temp  {:?}", temp.data);
+    }
+}
+
+fn print_locals(vm: &VM) {
+    let Some(frame) = vm.call_stack().first() else {
+        return;
+    };
+    if frame.locals.is_empty() {
+        println!("(no locals)");
+    }
+    for (name, obj) in &frame.locals {
+        println!("{} = {:?}", bytes_to_string(name), obj.data);
+    }
+}
+
+fn print_funcs(vm: &VM) {
+    if vm.program.funcs.is_empty() {
+        println!("(no functions)");
+    }
+    for (name, (idx, arity)) in &vm.program.funcs {
+        println!("{}/{arity} @ {idx}", bytes_to_string(name));
+    }
+}
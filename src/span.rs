@@ -1,6 +1,16 @@
 use std::fmt::Display;
 
-use crate::operation::Operation;
+use crate::{operation::Operation, utils};
+
+/// Where an instruction was written, lines and columns start at 1.
+#[derive(Clone, Copy, Debug)]
+pub struct SourceLocation {
+    pub file: &'static [u8],
+    pub line: usize,
+    pub column: usize,
+    // the whole line as written
+    pub text: &'static [u8],
+}
 
 #[derive(Clone, Debug)]
 pub struct Span {
@@ -8,6 +18,8 @@ pub struct Span {
     pub current_op: Operation,
     pub prev_op: Operation,
     pub next_op: Operation,
+    // `None` for Bytecode, which doesn't keep its source
+    pub(crate) source: Option<SourceLocation>,
 }
 
 impl Span {
@@ -17,6 +29,7 @@ impl Span {
             current_op: Operation::Empty,
             prev_op: Operation::Empty,
             next_op: Operation::Empty,
+            source: None,
         }
     }
 }
@@ -33,3 +46,31 @@ impl Display for Span {
         write!(f, "\t{}  {}", self.program_count + 1, self.next_op)
     }
 }
+
+impl Display for SourceLocation {
+    /// The line with a caret under the instruction:
+    /// ```text
+    ///  --> fib.jed:7:2
+    ///   |
+    /// 7 |     bin_op +
+    ///   |     ^^^^^^^^
+    /// ```
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = utils::bytes_to_string(self.text);
+        let text = text.trim_end();
+        let line_no = self.line.to_string();
+        let gutter = " ".repeat(line_no.len());
+        // keep the tabs, so the caret lines up however wide they are shown
+        let (indent, code) = text.split_at(self.column - 1);
+        writeln!(
+            f,
+            "{gutter}--> {}:{}:{}",
+            utils::bytes_to_string(self.file),
+            self.line,
+            self.column
+        )?;
+        writeln!(f, "{gutter} |")?;
+        writeln!(f, "{line_no} | {text}")?;
+        write!(f, "{gutter} | {indent}{}", "^".repeat(code.chars().count()))
+    }
+}
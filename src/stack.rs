@@ -70,6 +70,38 @@ impl<T> Stack<T> {
         Ok(unsafe { ptr::read(self.ptr.as_ptr().add(self.len)) })
     }
 
+    /// Pops everything above the first `len` elements.
+    pub fn truncate(&mut self, len: usize) {
+        while self.len > len {
+            self.len -= 1;
+            unsafe { drop(ptr::read(self.ptr.as_ptr().add(self.len))) };
+        }
+    }
+
+    /// Pushes a copy of the element `n` below the top, `0` being the top itself.
+    pub fn pick(&mut self, n: usize) -> Result<(), ProgramErrorKind>
+    where
+        T: Copy,
+    {
+        if n >= self.len {
+            return Err(ProgramErrorKind::StackError(n + 1));
+        }
+        let elem = unsafe { *self.ptr.as_ptr().add(self.len - 1 - n) };
+        self.push(elem);
+        Ok(())
+    }
+
+    /// Moves the element `n - 1` below the top up to the top, the ones above it
+    /// each go down one.
+    pub fn rotate(&mut self, n: usize) -> Result<(), ProgramErrorKind> {
+        if n > self.len {
+            return Err(ProgramErrorKind::StackError(n));
+        }
+        let top = unsafe { std::slice::from_raw_parts_mut(self.ptr.as_ptr().add(self.len - n), n) };
+        top.rotate_left(1.min(n));
+        Ok(())
+    }
+
     pub fn pop_mut(&mut self) -> Result<&mut T, ProgramErrorKind> {
         if self.len == 0 {
             return Err(ProgramErrorKind::StackError(1));
@@ -106,13 +138,6 @@ impl<T> Stack<T> {
             Ok(unsafe { &mut *self.ptr.as_ptr().add(self.len - 1) })
         }
     }
-    pub fn last_mut_option(&mut self) -> Option<&mut T> {
-        if self.len == 0 {
-            None
-        } else {
-            unsafe { Some(&mut *self.ptr.as_ptr().add(self.len - 1)) }
-        }
-    }
     pub fn last_option(&mut self) -> Option<&T> {
         if self.len == 0 {
             None
@@ -130,6 +155,10 @@ impl<T> Stack<T> {
         }
     }
 
+    pub fn as_slice(&self) -> &[T] {
+        unsafe { std::slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+
     pub unsafe fn at_most_n(&self, n: usize) -> Result<&[T], ProgramErrorKind> {
         let num = n.min(self.len);
         let nth = &*self.ptr.as_ptr().add(self.len - num);
@@ -27,23 +27,50 @@ where
     }
 }
 
-pub fn string_is_float_like(str: String) -> bool {
-    let mut has_dot = false;
-    for c in str.chars() {
-        if c == '.' {
-            if !has_dot {
-                has_dot = true;
-            } else {
-                return false;
-            }
-        }
-        if c.is_numeric() {
-            continue;
-        } else {
-            break;
+/// The value of a `"..."` string literal, `None` if `literal` isn't one.
+pub fn string_literal(literal: &str) -> Option<Result<String, String>> {
+    let inner = literal.strip_prefix('"')?;
+    match inner.strip_suffix('"') {
+        Some(inner) => Some(unescape(inner)),
+        None => Some(Err(format!("unterminated string {literal}"))),
+    }
+}
+
+/// Resolves the `\n`, `\t`, `\\`, `\"` and `\u{...}` escapes inside a string literal.
+pub fn unescape(s: &str) -> Result<String, String> {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('n') => out.push('\n'),
+                Some('t') => out.push('\t'),
+                Some('\\') => out.push('\\'),
+                Some('"') => out.push('"'),
+                Some('u') => {
+                    let rest = chars.as_str();
+                    let hex = rest
+                        .strip_prefix('{')
+                        .and_then(|r| r.split_once('}'))
+                        .map(|(hex, _)| hex)
+                        .ok_or("expected '{' after '\\u' and a closing '}'")?;
+                    let code = u32::from_str_radix(hex, 16)
+                        .ok()
+                        .filter(|_| (1..=6).contains(&hex.len()))
+                        .and_then(char::from_u32)
+                        .ok_or(format!("'\\u{{{hex}}}' is not a unicode character"))?;
+                    out.push(code);
+                    // skip past the braces
+                    chars = rest[hex.len() + 2..].chars();
+                }
+                Some(other) => return Err(format!("unknown escape '\\{other}'")),
+                None => return Err("string ends in an unfinished escape".to_owned()),
+            },
+            '"' => return Err("unescaped '\"' inside a string".to_owned()),
+            _ => out.push(c),
         }
     }
-    return has_dot;
+    Ok(out)
 }
 
 pub fn unwrap_as_string_or<T>(option: Option<T>, or: &str) -> String
@@ -59,7 +86,3 @@ where
 pub fn isize_to_usize(i: isize) -> usize {
     unsafe { std::mem::transmute(i) }
 }
-
-pub fn bounded(min: usize, v: usize, max: usize) -> bool {
-    return v >= min && v <= max;
-}
@@ -1,11 +1,17 @@
-use std::{collections::HashMap, fs::File, io};
+use std::{
+    collections::HashMap,
+    convert::TryFrom,
+    fs::File,
+    io::{self, Write},
+};
 
 use crate::{
     arena,
     binops::{self, BinOpKind},
-    error::{ProgramError, ProgramErrorKind},
+    error::{ParseError, ProgramError, ProgramErrorKind},
     frame::{Frame, FrameKind},
-    object::Object,
+    literal::Literal,
+    object::{Object, ObjectData, ObjectKind},
     operation::Operation,
     program::Program,
     span::Span,
@@ -13,26 +19,59 @@ use crate::{
     utils,
 };
 
+/// Gets a look at the VM right before each instruction runs, `op` is the
+/// instruction at `vm.counter`.
+pub trait Hook {
+    fn before_op(&mut self, vm: &VM, op: Operation);
+
+    /// Called once `VM::run` is done with the program.
+    fn after_run(&mut self, _vm: &VM) {}
+}
+
 pub struct VM {
     pub program: Program,
     pub consts: HashMap<&'static [u8], &'static Object>,
     pub counter: usize,
-    pub call_stack: Stack<Frame>,
-    pub obj_stack: Stack<&'static Object>,
+    pub(crate) call_stack: Stack<Frame>,
+    pub(crate) obj_stack: Stack<&'static Object>,
     pub temp: Option<&'static Object>,
-    pub memory: arena::Manual<Object>,
+    pub(crate) memory: arena::Manual<Object>,
     pub current_span: Span,
     pub debug: bool,
+    // with `debug` on, only these functions are traced, all of them if empty
+    pub trace_funcs: Vec<String>,
+    pub hooks: Vec<Box<dyn Hook>>,
+    // where `println` writes, stdout unless the host points it elsewhere
+    pub output: Box<dyn Write>,
+    // set by `exit`, stops `run` without touching the host process
+    pub halted: bool,
+    // set while blocks are being left early, every `run_block` returns until
+    // the frame it's meant for handles it
+    pub unwind: Option<Unwind>,
+    // how many `run_block`s and `run_until`s are running inside each other
+    block_depth: usize,
+}
+
+/// Blocks run as nested Rust calls, so recursing through a `do_if` goes
+/// deeper into the native stack. Past this many it's a `CallStackOverflow`,
+/// which leaves room on a 2MB thread even in a debug build.
+const MAX_BLOCK_DEPTH: usize = 200;
+
+/// Why the blocks being run are being left early.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unwind {
+    Break,
+    Continue,
+    Return,
 }
 
 impl VM {
     pub fn new(program: Program, debug: bool) -> Self {
         let mut call_stack = Stack::new();
         call_stack.push(Frame::new(program.instructions.len(), FrameKind::Main));
-        let main = program.get_main();
         VM {
             call_stack,
-            counter: main,
+            counter: 0,
             program,
             consts: HashMap::new(),
             obj_stack: Stack::new(),
@@ -40,7 +79,32 @@ impl VM {
             memory: Default::default(),
             current_span: Span::empty(),
             debug,
+            trace_funcs: vec![],
+            hooks: vec![],
+            output: Box::new(io::stdout()),
+            halted: false,
+            unwind: None,
+            block_depth: 0,
+        }
+    }
+
+    /// The frames of the calls and blocks being run, innermost last.
+    pub fn call_stack(&self) -> &[Frame] {
+        self.call_stack.as_slice()
+    }
+
+    /// The object stack, top last.
+    pub fn obj_stack(&self) -> &[&'static Object] {
+        self.obj_stack.as_slice()
+    }
+
+    /// Drops every frame above `main`'s and stops unwinding, for carrying on
+    /// with the VM after a runtime error.
+    pub fn leave_to_main(&mut self) {
+        while self.call_stack.len() > 1 {
+            let _ = self.call_stack.pop();
         }
+        self.unwind = None;
     }
 
     pub fn register_single(&mut self, obj: Object) -> &'static Object {
@@ -53,6 +117,32 @@ impl VM {
         saved_bytes
     }
 
+    /// Copies `objects` into VM memory and registers a `List` pointing at them.
+    pub fn alloc_list(&mut self, objects: &[Object]) -> &'static Object {
+        let (start, len) = if !objects.is_empty() {
+            let objects: &'static [Object] = self.register_many(objects);
+            (objects.as_ptr().addr(), objects.len())
+        } else {
+            (self.memory.start().addr(), 0)
+        };
+        let obj = Object {
+            kind: ObjectKind::List,
+            data: ObjectData::List(Box::into_raw(Box::new(start)), Box::into_raw(Box::new(len))),
+        };
+        self.register_single(obj)
+    }
+
+    /// Registers a parsed `push_lit` literal, lists the same way `create_list` builds them.
+    pub(crate) fn alloc_literal(&mut self, literal: &Literal) -> &'static Object {
+        match literal {
+            Literal::Value(obj) => self.register_single(*obj),
+            Literal::List(items) => {
+                let objects: Vec<Object> = items.iter().map(|item| *self.alloc_literal(item)).collect();
+                self.alloc_list(&objects)
+            }
+        }
+    }
+
     pub fn drop(&mut self, obj: &'static Object) {
         self.memory.deallocate(
             obj as *const Object as *mut Object,
@@ -77,91 +167,101 @@ impl VM {
         self.consts.get(name).map(|v| &**v)
     }
 
-    pub fn from_string(text: String, debug: bool) -> Self {
-        let program = Program::from_string(text);
-        Self::new(program, debug)
+    pub fn from_string(text: &str, debug: bool) -> Result<Self, ParseError> {
+        let program = Program::from_string(text)?;
+        Ok(Self::new(program, debug))
     }
-    pub fn from_file(file: &mut File, debug: bool) -> io::Result<Self> {
+    pub fn from_file(file: &mut File, debug: bool) -> Result<Self, ParseError> {
         let program = Program::from_file(file)?;
         Ok(Self::new(program, debug))
     }
 
-    pub fn run(&mut self) {
-        self.counter = self.program.get_main();
+    /// Runs `main`, returning whatever is on top of the object stack once it
+    /// is done or hits an `exit`.
+    pub fn run(&mut self) -> Result<Option<Object>, ProgramError> {
+        self.run_with_args(&[])
+    }
+
+    /// Like `run`, a `func main 1` gets `args` as a `List` of `String`s.
+    pub fn run_with_args(&mut self, args: &[String]) -> Result<Option<Object>, ProgramError> {
+        self.counter = match self.program.get_main() {
+            Ok(main) => main,
+            Err(e) => return self.error(e),
+        };
+        if let Some((_, 1)) = self.program.funcs.get("main".as_bytes()) {
+            let args: Vec<Object> = args
+                .iter()
+                .map(|arg| Object {
+                    kind: ObjectKind::String,
+                    data: ObjectData::String(self.program.register(arg.clone())),
+                })
+                .collect();
+            let list = self.alloc_list(&args);
+            self.obj_stack.push(list);
+        }
+        self.halted = false;
+        let res = self.run_main();
+
+        let mut hooks = std::mem::take(&mut self.hooks);
+        for hook in &mut hooks {
+            hook.after_run(self);
+        }
+        self.hooks = hooks;
+
+        res?;
+        Ok(self.obj_stack.as_slice().last().map(|obj| **obj))
+    }
+
+    fn run_main(&mut self) -> Result<(), ProgramError> {
         loop {
-            self.update_span();
             if self.counter == self.program.instructions.len() - 1 {
-                return;
-            }
-            if self.call_stack.len() > 100_000 {
-                panic!("call stack overflow");
-            }
-            if self.obj_stack.len() > 1_000_000 {
-                unsafe {
-                    panic!(
-                        "object stack overflow, {:?}",
-                        self.obj_stack
-                            .last_n(10)
-                            .iter()
-                            .map(|x| format!("{:?}", x))
-                            .collect::<Vec<String>>()
-                    )
-                }
-            }
-            let op = self.next();
-            let res = op.unwrap().call(self);
-
-            match res {
-                Ok(_) => {}
-                Err(e) => {
-                    println!("\nruntime failure:\n{}", e);
-                    std::process::exit(1);
-                }
+                return Ok(());
             }
+            self.step()?;
+            // a `return` from blocks of a function called up here has nothing left to unwind
+            self.unwind = None;
 
+            if self.halted {
+                return Ok(());
+            }
             if let Operation::Exit = self.program.get_op(self.counter) {
-                return;
+                return Ok(());
             }
         }
     }
 
-    pub fn run_block(&mut self, frame_type: FrameKind) {
+    /// Runs from `pc` until the counter walks off the end of the instructions,
+    /// used by the repl where top level code is appended after everything else.
+    pub fn run_from(&mut self, pc: usize) -> Result<(), ProgramError> {
+        self.counter = pc;
+        while self.counter < self.program.instructions.len() && !self.halted {
+            self.step()?;
+            self.unwind = None;
+        }
+        Ok(())
+    }
+
+    pub fn run_block(&mut self, frame_type: FrameKind) -> Result<(), ProgramError> {
+        self.nested(|vm| vm.run_block_steps(frame_type))
+    }
+
+    fn run_block_steps(&mut self, frame_type: FrameKind) -> Result<(), ProgramError> {
+        // the block's own frame is on top when it starts
+        let depth = self.call_stack.len();
         loop {
-            self.update_span();
             if self.counter == self.program.instructions.len() - 1 {
-                return;
-            }
-            if self.call_stack.len() > 100_000 {
-                panic!("call stack overflow");
+                return Ok(());
             }
-            if self.obj_stack.len() > 1_000_000 {
-                unsafe {
-                    panic!(
-                        "object stack overflow, {:?}",
-                        self.obj_stack
-                            .last_n(10)
-                            .iter()
-                            .map(|x| format!("{:?}", x))
-                            .collect::<Vec<String>>()
-                    )
-                }
-            }
-            let op = self.next();
-            let res = op.unwrap().call(self);
-
-            match res {
-                Ok(_) => {}
-                Err(e) => {
-                    println!("\nruntime failure:\n{}", e);
-                    std::process::exit(1);
-                }
+            self.step()?;
+            if self.block_unwound(depth) {
+                return Ok(());
             }
 
             if let Ok(frame) = self.call_stack.last() {
                 let op = self.program.get_op(self.counter);
                 if frame.kind == frame_type {
                     match op {
-                        Operation::Done | Operation::Exit => return,
+                        Operation::Done | Operation::Exit => return Ok(()),
                         _ => (),
                     }
                 }
@@ -169,12 +269,244 @@ impl VM {
         }
     }
 
-    pub fn exit(&mut self, code: Option<i32>) {
-        self.counter = self.program.get_main();
-        self.obj_stack = Stack::new();
-        self.call_stack = Stack::new();
-        self.program.memos.clear();
-        std::process::exit(code.unwrap_or_default());
+    /// Runs until the counter reaches `pc`, like the condition of a `do_while`
+    /// running up to its `check`.
+    pub fn run_until(&mut self, pc: usize) -> Result<(), ProgramError> {
+        self.nested(|vm| {
+            let depth = vm.call_stack.len();
+            while vm.counter != pc && vm.counter < vm.program.instructions.len() {
+                vm.step()?;
+                if vm.block_unwound(depth) {
+                    return Ok(());
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /// Runs `steps` one block deeper, see `MAX_BLOCK_DEPTH`.
+    fn nested<F>(&mut self, steps: F) -> Result<(), ProgramError>
+    where
+        F: FnOnce(&mut Self) -> Result<(), ProgramError>,
+    {
+        if self.block_depth >= MAX_BLOCK_DEPTH {
+            return self.error(ProgramErrorKind::CallStackOverflow);
+        }
+        self.block_depth += 1;
+        let res = steps(self);
+        self.block_depth -= 1;
+        res
+    }
+
+    /// Whether a block whose frame sits at `depth` stops after a step, because
+    /// the program halted or the block is being left through `unwind`.
+    fn block_unwound(&mut self, depth: usize) -> bool {
+        if self.halted {
+            return true;
+        }
+        match self.unwind {
+            // the return was from a function called inside this block, which goes on
+            Some(Unwind::Return) if self.call_stack.len() >= depth => {
+                self.unwind = None;
+                false
+            }
+            Some(_) => true,
+            None => false,
+        }
+    }
+
+    /// Whether the loop that just ran a pass was left by a `return`, its frame
+    /// is gone then and the counter is already back in the caller.
+    pub fn returning(&self) -> bool {
+        self.halted || self.unwind == Some(Unwind::Return)
+    }
+
+    /// Marks the start of a loop's pass on its frame, see `unwind_to_loop`.
+    pub fn start_pass(&mut self) {
+        let height = self.obj_stack.len();
+        if let Ok(frame) = self.call_stack.last_mut() {
+            frame.stack_height = height;
+        }
+    }
+
+    /// Leaves the innermost loop's current pass for a `break` or `continue`.
+    ///
+    /// The `do_if` frames in between are popped and the object stack goes back
+    /// to where it was when the pass started. The loop itself sees `unwind`
+    /// once every `run_block` in between has returned.
+    pub fn unwind_to_loop(&mut self, unwind: Unwind) -> Result<(), ProgramError> {
+        loop {
+            let kind = match self.call_stack.last() {
+                Ok(frame) => frame.kind.clone(),
+                Err(e) => return self.error(e),
+            };
+            match kind {
+                FrameKind::DoIfBlock => {
+                    let _ = self.call_stack.pop();
+                }
+                kind if kind.is_loop() => break,
+                _ => return self.error(ProgramErrorKind::NoLoop),
+            }
+        }
+        let height = match self.call_stack.last() {
+            Ok(frame) => frame.stack_height,
+            Err(e) => return self.error(e),
+        };
+        self.obj_stack.truncate(height);
+        self.unwind = Some(unwind);
+        Ok(())
+    }
+
+    /// Returns from the innermost function, through every block frame it's in.
+    ///
+    /// The top of the object stack is the return value, memoized the same way
+    /// `done` does it. Returning from `main` ends the program.
+    pub fn return_from_function(&mut self) -> Result<(), ProgramError> {
+        let mut left_blocks = false;
+        loop {
+            let frame = match self.call_stack.pop() {
+                Ok(frame) => frame,
+                Err(e) => return self.error(e),
+            };
+            match frame.kind {
+                FrameKind::Call => {
+                    self.finish_call(&frame);
+                    break;
+                }
+                FrameKind::Main => {
+                    self.call_stack.push(frame);
+                    self.exit();
+                    return Ok(());
+                }
+                _ => left_blocks = true,
+            }
+        }
+        // the blocks that were left are still running their `run_block`s
+        if left_blocks {
+            self.unwind = Some(Unwind::Return);
+        }
+        Ok(())
+    }
+
+    /// Memoizes the return value of a call whose frame was just popped and goes
+    /// back to the caller.
+    pub fn finish_call(&mut self, frame: &Frame) {
+        let nil = Object::nil();
+        let return_value = **self.obj_stack.last_option().unwrap_or(&&nil);
+        self.program.set_memo(frame.memo_key, return_value);
+        self.counter = frame.return_address;
+    }
+
+    /// Called by a loop after each pass, whether the loop ends there. A `break`
+    /// or `continue` that cut the pass short is handled and cleared.
+    pub fn pass_ended_loop(&mut self) -> bool {
+        match self.unwind {
+            Some(Unwind::Break) => {
+                self.unwind = None;
+                true
+            }
+            Some(Unwind::Continue) => {
+                self.unwind = None;
+                false
+            }
+            // left for the blocks further out, the loop's frame is already gone
+            Some(Unwind::Return) => true,
+            None => false,
+        }
+    }
+
+    /// `pick n` on the object stack, copying the object `n` below the top.
+    pub fn pick(&mut self, n: usize) -> Result<(), ProgramError> {
+        match self.obj_stack.pick(n) {
+            Ok(()) => Ok(()),
+            Err(e) => self.error(e),
+        }
+    }
+
+    /// Brings the object `n - 1` below the top of the object stack up to the top.
+    pub fn rotate(&mut self, n: usize) -> Result<(), ProgramError> {
+        match self.obj_stack.rotate(n) {
+            Ok(()) => Ok(()),
+            Err(e) => self.error(e),
+        }
+    }
+
+    /// Pops the count for an op that was given none, it has to be a positive `Integer`.
+    pub fn pop_count(&mut self) -> Result<usize, ProgramError> {
+        let obj = match self.obj_stack.pop() {
+            Ok(obj) => obj,
+            Err(e) => return self.error(e),
+        };
+        match obj.as_tuple() {
+            (ObjectKind::Integer, ObjectData::Integer(n)) => match usize::try_from(n) {
+                Ok(n) => Ok(n),
+                Err(_) => self.error(ProgramErrorKind::IntegerToUnsigned),
+            },
+            (kind, _) => self.error(ProgramErrorKind::TypeError(ObjectKind::Integer, kind)),
+        }
+    }
+
+    fn step(&mut self) -> Result<(), ProgramError> {
+        self.update_span();
+        if self.call_stack.len() > 100_000 {
+            return self.error(ProgramErrorKind::CallStackOverflow);
+        }
+        if self.obj_stack.len() > 1_000_000 {
+            return self.error(ProgramErrorKind::ObjectStackOverflow);
+        }
+        if self.debug {
+            self.trace();
+        }
+        if !self.hooks.is_empty() {
+            let op = *self.program.get_op(self.counter);
+            let mut hooks = std::mem::take(&mut self.hooks);
+            for hook in &mut hooks {
+                hook.before_op(self, op);
+            }
+            self.hooks = hooks;
+        }
+        let op = self.next();
+        op.unwrap().call(self)
+    }
+
+    /// Prints the instruction about to run to stderr, with the kind of the
+    /// innermost frame and the object stack's depth and top.
+    fn trace(&self) {
+        let func = self
+            .call_stack
+            .as_slice()
+            .iter()
+            .rev()
+            .find(|frame| matches!(frame.kind, FrameKind::Call | FrameKind::Main))
+            .map_or("main".to_owned(), |frame| match frame.kind {
+                FrameKind::Call => self
+                    .program
+                    .func_name(frame.memo_key.0)
+                    .map_or("?".to_owned(), utils::bytes_to_string),
+                _ => "main".to_owned(),
+            });
+        if !self.trace_funcs.is_empty() && !self.trace_funcs.contains(&func) {
+            return;
+        }
+        let kind = match self.call_stack.last() {
+            Ok(frame) => format!("{:?}", frame.kind),
+            Err(_) => "-".to_owned(),
+        };
+        let top = match self.obj_stack.as_slice().last() {
+            Some(obj) => format!("{:?}", obj.data),
+            None => "-".to_owned(),
+        };
+        eprintln!(
+            "{:>6} {func:<12} {kind:<12} {:>4} {top:<24} {}",
+            self.counter,
+            self.obj_stack.as_slice().len(),
+            self.program.get_op(self.counter)
+        );
+    }
+
+    /// Stops the program, every running loop returns as soon as it sees `halted`.
+    pub fn exit(&mut self) {
+        self.halted = true;
     }
 
     fn update_span(&mut self) {
@@ -200,6 +532,7 @@ impl VM {
                 .instructions
                 .get(self.counter + 1)
                 .unwrap_or(&Operation::Empty),
+            source: self.program.source_map.get(self.counter).copied(),
         };
     }
 
@@ -251,6 +584,8 @@ impl VM {
             BinOpKind::Or => binops::or(lhs, rhs),
             BinOpKind::Power => binops::pow(lhs, rhs),
             BinOpKind::Root => binops::root(lhs, rhs),
+            BinOpKind::NotEq => binops::not_eq(lhs, rhs),
+            BinOpKind::FloorDiv => binops::floor_div(lhs, rhs),
         };
 
         match result {
@@ -262,6 +597,25 @@ impl VM {
         }
     }
 
+    /// Replaces the top of the object stack with `op` applied to it.
+    pub fn handle_un_op(
+        &mut self,
+        op: fn(Object) -> Result<Object, ProgramErrorKind>,
+    ) -> Result<(), ProgramError> {
+        let obj = match self.obj_stack.pop() {
+            Ok(obj) => *obj,
+            Err(e) => return self.error(e),
+        };
+        match op(obj) {
+            Ok(value) => {
+                let value = self.register_single(value);
+                self.obj_stack.push(value);
+                Ok(())
+            }
+            Err(e) => self.error(e),
+        }
+    }
+
     pub fn unwrap_or_error<T>(
         &self,
         option: Option<T>,
@@ -274,41 +628,114 @@ impl VM {
     }
 
     pub fn error<T>(&self, e: ProgramErrorKind) -> Result<T, ProgramError> {
-        match e {
-            ProgramErrorKind::VariableExists(_) => {
-                match self.call_stack.last().cloned() {
-                    Ok(frame) => println!(
-                        "current variables: {:?}",
-                        frame
-                            .locals
-                            .into_keys()
-                            .map(|x| utils::bytes_to_string(x))
-                            .collect::<Vec<String>>()
-                    ),
-                    Err(_) => todo!(),
-                };
-            }
-            ProgramErrorKind::StackError(_) => unsafe {
-                println!(
-                    "call stack: {:?}",
-                    self.call_stack
-                        .last_n(10)
-                        .iter()
-                        .map(|x| format!("{:?}", x))
-                        .collect::<Vec<String>>()
-                );
-                println!(
-                    "object stack: {:?}",
-                    self.obj_stack
-                        .last_n(10)
-                        .iter()
-                        .map(|x| format!("{:?}", x))
-                        .collect::<Vec<String>>()
-                );
-            },
-            _ => {}
+        Err(ProgramError(e, self.current_span.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::VM;
+    use crate::{
+        error::ProgramErrorKind,
+        object::{ObjectData, ObjectKind},
+    };
+
+    #[test]
+    fn run_returns_top_of_stack() {
+        let text = "func main 0\n\tpush_lit 1\n\tpush_lit 2\n\tbin_op +\ndone\n";
+        let mut vm = VM::from_string(text, false).expect("parses");
+        let result = vm.run().expect("runs");
+        assert_eq!(result.map(|obj| obj.data), Some(ObjectData::Integer(3)));
+    }
+
+    #[test]
+    fn main_gets_args_as_list() {
+        let text = "func main 1\ndone\n";
+        let mut vm = VM::from_string(text, false).expect("parses");
+        let args = ["a".to_owned(), "bc".to_owned()];
+        let result = vm.run_with_args(&args).expect("runs");
+        match result.map(|obj| obj.data) {
+            Some(ObjectData::List(_, len)) => assert_eq!(unsafe { *len }, 2),
+            other => panic!("expected a list, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn do_while_checks_before_every_pass() {
+        // sums 0..=3 on the stack, the condition's bools must not be left behind
+        let text = "func main 0\n\tpush_lit 0\n\tpush_lit 0\n\tstore_name i\n\tdo_while\n\t\tpush_name i\n\t\tpush_lit 3\n\t\tbin_op <=\n\tcheck\n\t\tpush_name i\n\t\tbin_op +\n\t\tpush_name i\n\t\tpush_lit 1\n\t\tbin_op +\n\t\tstore_name i\n\tdone\ndone\n";
+        let mut vm = VM::from_string(text, false).expect("parses");
+        let result = vm.run().expect("runs");
+        assert_eq!(result.map(|obj| obj.data), Some(ObjectData::Integer(6)));
+        assert_eq!(vm.obj_stack.as_slice().len(), 1);
+    }
+
+    #[test]
+    fn break_leaves_nested_blocks_and_their_stack() {
+        let text = "func main 0\n\tpush_lit 1\n\tpush_lit 3\n\tdo_for\n\t\tpush_lit \"junk\"\n\t\tpush_lit true\n\t\tdo_if\n\t\t\tbreak\n\t\tdone\n\tdone\n\tpush_lit 2\n\tbin_op +\ndone\n";
+        let mut vm = VM::from_string(text, false).expect("parses");
+        let result = vm.run().expect("runs");
+        assert_eq!(result.map(|obj| obj.data), Some(ObjectData::Integer(3)));
+        assert_eq!(vm.obj_stack.as_slice().len(), 1);
+        assert_eq!(vm.call_stack.as_slice().len(), 1);
+    }
+
+    #[test]
+    fn return_leaves_the_function_from_nested_blocks() {
+        let text = "func pick 0\n\tpush_lit 3\n\tdo_for\n\t\tpush_lit true\n\t\tdo_if\n\t\t\tpush_lit 7\n\t\t\treturn\n\t\tdone\n\tdone\n\tpush_lit 0\ndone\nfunc main 0\n\tpush_lit 2\n\tdo_for\n\t\tcall pick\n\tdone\n\tbin_op +\ndone\n";
+        let mut vm = VM::from_string(text, false).expect("parses");
+        let result = vm.run().expect("runs");
+        // both passes of main's loop ran, each call left its 7 behind
+        assert_eq!(result.map(|obj| obj.data), Some(ObjectData::Integer(14)));
+        assert_eq!(vm.obj_stack.as_slice().len(), 1);
+        assert_eq!(vm.call_stack.as_slice().len(), 1);
+    }
+
+    #[test]
+    fn stack_shuffles() {
+        let run = |body: &str| {
+            let text = format!("func main 0\n{body}\n\tcreate_list\ndone\n");
+            let mut vm = VM::from_string(&text, false).expect("parses");
+            vm.run().map(|obj| obj.expect("a list").to_string())
         };
+        let three = "\tpush_lit 1\n\tpush_lit 2\n\tpush_lit 3\n";
+        assert_eq!(run(&format!("{three}\trot")).unwrap(), "[2,3,1]");
+        assert_eq!(run(&format!("{three}\tswap\n\tover")).unwrap(), "[1,3,2,3]");
+        assert_eq!(run(&format!("{three}\tpick 2\n\tdup")).unwrap(), "[1,2,3,1,1]");
+        assert_eq!(run(&format!("{three}\tpush_lit 2\n\tdrop")).unwrap(), "[1]");
 
-        Err(ProgramError(e, self.current_span.clone()))
+        let err = run(&format!("{three}\tpick 3")).expect_err("only 3 objects");
+        assert!(matches!(err.0, ProgramErrorKind::StackError(4)));
+        let err = run("\tpush_lit 1\n\trot").expect_err("only 1 object");
+        assert!(matches!(err.0, ProgramErrorKind::StackError(3)));
+    }
+
+    #[test]
+    fn run_returns_runtime_errors() {
+        let text = "func main 0\n\tpush_lit 1\n\tpush_lit \"a\"\n\tbin_op +\ndone\n";
+        let mut vm = VM::from_string(text, false).expect("parses");
+        let err = vm.run().expect_err("can't add a string to an int");
+        assert!(matches!(err.0, ProgramErrorKind::BinopError(..)));
+    }
+
+    #[test]
+    fn bad_programs_fail_instead_of_panicking() {
+        let fail = |text: &str| VM::from_string(text, false).expect("parses").run().expect_err(text).0;
+        assert!(matches!(fail("func foo 0\ndone\n"), ProgramErrorKind::NoMain));
+        assert!(matches!(
+            fail("func main 0\n\tpush_lit 1\n\tdo_if\n\tdone\ndone\n"),
+            ProgramErrorKind::TypeError(ObjectKind::Bool, ObjectKind::Integer)
+        ));
+        assert!(matches!(
+            fail("func f 0\n\tcall f\ndone\nfunc main 0\n\tcall f\ndone\n"),
+            ProgramErrorKind::CallStackOverflow
+        ));
+        let err = fail("func main 0\n\tpush_lit true\n\treturn_if_const nope\ndone\n");
+        assert_eq!(err.to_string(), "constant 'nope' does not exist");
+        // every call goes one `run_block` deeper
+        assert!(matches!(
+            fail("func f 0\n\tpush_lit true\n\tdo_if\n\t\tcall f\n\tdone\ndone\nfunc main 0\n\tcall f\ndone\n"),
+            ProgramErrorKind::CallStackOverflow
+        ));
     }
 }